@@ -1,5 +1,11 @@
+pub mod auth;
 pub mod http;
+pub mod parser;
+pub mod socket;
 pub mod stdio;
+pub mod tls;
+use crate::command::Id;
+use crate::session::Event;
 use std::str::FromStr;
 
 #[derive(Debug, Default, Copy, Clone)]
@@ -8,6 +14,19 @@ pub struct Subscription {
     snapshot: bool,
     resize: bool,
     output: bool,
+    input: bool,
+    wait_result: bool,
+    idle: bool,
+    ack: bool,
+    error: bool,
+    scrollback: bool,
+    view: bool,
+    exit: bool,
+    title: bool,
+    bell: bool,
+    notification: bool,
+    screenshot: bool,
+    control: bool,
 }
 
 impl FromStr for Subscription {
@@ -20,8 +39,21 @@ impl FromStr for Subscription {
             match event {
                 "init" => sub.init = true,
                 "output" => sub.output = true,
+                "input" => sub.input = true,
                 "resize" => sub.resize = true,
                 "snapshot" => sub.snapshot = true,
+                "waitResult" => sub.wait_result = true,
+                "idle" => sub.idle = true,
+                "ack" => sub.ack = true,
+                "error" => sub.error = true,
+                "scrollback" => sub.scrollback = true,
+                "view" => sub.view = true,
+                "exit" => sub.exit = true,
+                "title" => sub.title = true,
+                "bell" => sub.bell = true,
+                "notification" => sub.notification = true,
+                "screenshot" => sub.screenshot = true,
+                "control" => sub.control = true,
                 _ => return Err(format!("invalid event name: {event}")),
             }
         }
@@ -29,3 +61,35 @@ impl FromStr for Subscription {
         Ok(sub)
     }
 }
+
+impl Subscription {
+    /// Tells whether the event is one of the subscribed ones.
+    pub fn includes(&self, event: &Event) -> bool {
+        match event {
+            Event::Init(_, _, _, _, _, _, _, _, _) => self.init,
+            Event::Output(_, _) => self.output,
+            Event::Input(_, _) => self.input,
+            Event::Resize(_, _, _) => self.resize,
+            Event::Snapshot(_, _, _, _, _, _, _, _) => self.snapshot,
+            Event::WaitResult(_, _) => self.wait_result,
+            Event::Idle => self.idle,
+            Event::Ack(_) => self.ack,
+            Event::Error(_, _) => self.error,
+            Event::Scrollback(_, _, _, _, _) => self.scrollback,
+            Event::View(_, _, _) => self.view,
+            Event::Exit(_) => self.exit,
+            Event::Title(_) => self.title,
+            Event::Bell => self.bell,
+            Event::Notification(_, _) => self.notification,
+            Event::Screenshot(_, _, _, _) => self.screenshot,
+            Event::Control(_) => self.control,
+        }
+    }
+
+    /// Tells whether to report the error of a command which can't be parsed.
+    /// Commands carrying an id always get it, as their sender waits for the
+    /// result.
+    pub fn reports_parse_error(&self, id: &Option<Id>) -> bool {
+        self.error || id.is_some()
+    }
+}
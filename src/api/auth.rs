@@ -0,0 +1,233 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Read};
+
+/// Name of the cookie remembering the token for browsers.
+pub const COOKIE_NAME: &str = "ht_token";
+
+/// Tokens required by the HTTP server. The read-write token is always
+/// required to control the terminal, while watching it may be public.
+#[derive(Debug, Clone)]
+pub struct Auth {
+    read_write: String,
+    read_only: Option<String>,
+    public_read: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    ReadOnly,
+    ReadWrite,
+}
+
+impl Auth {
+    pub fn new(read_write: String, read_only: Option<String>, public_read: bool) -> Self {
+        Self {
+            read_write,
+            read_only,
+            public_read,
+        }
+    }
+
+    /// Whether watching the terminal doesn't require a token.
+    pub fn is_public_read(&self) -> bool {
+        self.public_read
+    }
+
+    /// Resolves the access level granted by the token, or to requests without
+    /// a valid one.
+    pub fn access(&self, token: Option<&str>) -> Option<Access> {
+        match token {
+            Some(token) if equal(&self.read_write, token) => Some(Access::ReadWrite),
+
+            Some(token) if self.read_only.as_deref().is_some_and(|t| equal(t, token)) => {
+                Some(Access::ReadOnly)
+            }
+
+            _ if self.public_read => Some(Access::ReadOnly),
+
+            _ => None,
+        }
+    }
+
+    /// The token to put in the live preview URL, which only needs read access.
+    pub fn preview_token(&self) -> Option<&str> {
+        if self.public_read {
+            None
+        } else {
+            Some(self.read_only.as_deref().unwrap_or(&self.read_write))
+        }
+    }
+
+    /// The token required to control the terminal.
+    pub fn control_token(&self) -> &str {
+        &self.read_write
+    }
+}
+
+/// Generates a random token of 32 hex digits.
+pub fn generate_token() -> io::Result<String> {
+    let mut bytes = [0u8; 16];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Percent-encodes the token for the query param or the cookie.
+pub fn encode(token: &str) -> String {
+    utf8_percent_encode(token, NON_ALPHANUMERIC).to_string()
+}
+
+/// Finds the token in the request: `Authorization: Bearer` header, `token`
+/// query param or the cookie, in this order. Also tells whether it came from
+/// the query param.
+pub fn find_token<'a>(
+    authorization: Option<&'a str>,
+    query: Option<&'a str>,
+    cookie: Option<&'a str>,
+) -> Option<(Cow<'a, str>, bool)> {
+    let header = authorization.and_then(|value| value.strip_prefix("Bearer "));
+
+    let query = query.and_then(|query| {
+        query
+            .split('&')
+            .find_map(|param| param.strip_prefix("token="))
+    });
+
+    let cookie = cookie.and_then(|cookie| {
+        cookie.split(';').find_map(|c| {
+            c.trim()
+                .strip_prefix(COOKIE_NAME)
+                .and_then(|c| c.strip_prefix('='))
+        })
+    });
+
+    let decode = |t: &'a str| percent_decode_str(t).decode_utf8_lossy();
+
+    header
+        .map(|t| (Cow::from(t.trim()), false))
+        .or(query.map(|t| (decode(t), true)))
+        .or(cookie.map(|t| (decode(t), false)))
+}
+
+/// Tells whether the `Origin` header of a request is the server itself, as
+/// addressed by the `Host` header, so that other web pages open in the
+/// browser can't connect to the terminal.
+pub fn same_origin(origin: &str, host: &str) -> bool {
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return false;
+    };
+
+    let default_port = match scheme {
+        "http" => ":80",
+        "https" => ":443",
+        _ => return false,
+    };
+
+    let normalize = |h: &str| {
+        h.strip_suffix(default_port)
+            .unwrap_or(h)
+            .to_ascii_lowercase()
+    };
+
+    normalize(authority) == normalize(host)
+}
+
+/// Compares tokens in constant time, not to leak how much of a guess matches.
+fn equal(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, find_token, generate_token, same_origin, Access, Auth};
+    use std::borrow::Cow;
+
+    #[test]
+    fn access() {
+        let auth = Auth::new("rw".to_owned(), Some("ro".to_owned()), false);
+
+        assert_eq!(auth.access(Some("rw")), Some(Access::ReadWrite));
+        assert_eq!(auth.access(Some("ro")), Some(Access::ReadOnly));
+        assert_eq!(auth.access(Some("r")), None);
+        assert_eq!(auth.access(Some("")), None);
+        assert_eq!(auth.access(None), None);
+        assert_eq!(auth.preview_token(), Some("ro"));
+        assert_eq!(auth.control_token(), "rw");
+
+        let auth = Auth::new("rw".to_owned(), None, true);
+
+        assert_eq!(auth.access(Some("rw")), Some(Access::ReadWrite));
+        assert_eq!(auth.access(Some("r")), Some(Access::ReadOnly));
+        assert_eq!(auth.access(None), Some(Access::ReadOnly));
+        assert_eq!(auth.preview_token(), None);
+    }
+
+    #[test]
+    fn origin() {
+        assert!(same_origin("http://127.0.0.1:8080", "127.0.0.1:8080"));
+        assert!(same_origin("https://Example.com", "example.com"));
+        assert!(same_origin("https://example.com", "example.com:443"));
+        assert!(same_origin("http://[::1]:80", "[::1]"));
+
+        assert!(!same_origin("https://evil.example", "127.0.0.1:8080"));
+        assert!(!same_origin("http://127.0.0.1:8081", "127.0.0.1:8080"));
+        assert!(!same_origin("http://example.com", "example.com:443"));
+        assert!(!same_origin("null", "127.0.0.1:8080"));
+        assert!(!same_origin("file://", ""));
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            find_token(Some("Bearer abc"), None, None),
+            Some((Cow::from("abc"), false))
+        );
+        assert_eq!(find_token(Some("Basic abc"), None, None), None);
+        assert_eq!(
+            find_token(None, Some("a=1&token=abc"), None),
+            Some((Cow::from("abc"), true))
+        );
+        assert_eq!(find_token(None, Some("tokens=abc"), None), None);
+        assert_eq!(
+            find_token(None, None, Some("x=1; ht_token=abc")),
+            Some((Cow::from("abc"), false))
+        );
+        assert_eq!(find_token(None, None, Some("ht_tokens=abc")), None);
+
+        assert_eq!(
+            find_token(Some("Bearer h"), Some("token=q"), Some("ht_token=c")),
+            Some((Cow::from("h"), false))
+        );
+
+        assert_eq!(
+            find_token(None, Some("token=q"), Some("ht_token=c")),
+            Some((Cow::from("q"), true))
+        );
+
+        assert_eq!(
+            find_token(None, Some("token=a%2Bb%26c%20d"), None),
+            Some((Cow::from("a+b&c d"), true))
+        );
+
+        assert_eq!(
+            find_token(None, None, Some("ht_token=a%3Bb")),
+            Some((Cow::from("a;b"), false))
+        );
+
+        assert_eq!(encode("a+b&c d;é"), "a%2Bb%26c%20d%3B%C3%A9");
+        assert_eq!(
+            find_token(None, Some(&format!("token={}", encode("a+b&c d;é"))), None),
+            Some((Cow::from("a+b&c d;é"), true))
+        );
+
+        let token = generate_token().unwrap();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, generate_token().unwrap());
+    }
+}
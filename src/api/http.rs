@@ -1,14 +1,25 @@
-use super::Subscription;
+use super::auth::{self, Access, Auth};
+use super::{parser, Subscription};
+use crate::command::{ClientId, Command, Reply, Request};
+use crate::render;
 use crate::session;
 use anyhow::Result;
 use axum::{
-    extract::{connect_info::ConnectInfo, ws, Query, State},
-    http::{header, StatusCode, Uri},
-    response::IntoResponse,
-    routing::get,
-    Router,
+    extract::{
+        connect_info::ConnectInfo,
+        rejection::{JsonRejection, QueryRejection},
+        ws, FromRef, Query, State,
+    },
+    http::{header, HeaderValue, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
 };
-use futures_util::{sink, stream, StreamExt};
+use futures_util::future::Either;
+use futures_util::{sink, stream, SinkExt, StreamExt};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
 use rust_embed::RustEmbed;
 use serde::Deserialize;
 use serde_json::json;
@@ -16,34 +27,250 @@ use std::borrow::Cow;
 use std::future::{self, Future, IntoFuture};
 use std::io;
 use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
 use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tower_service::Service;
 
 #[derive(RustEmbed)]
 #[folder = "assets/"]
 struct Assets;
 
+/// How long a REST request waits for the result of its command.
+const RESULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a client has to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sequence number for ids of commands sent by REST requests.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone)]
+struct AppState {
+    command_tx: mpsc::Sender<Request>,
+    clients_tx: mpsc::Sender<session::Client>,
+    interactive_preview: bool,
+}
+
+impl FromRef<AppState> for mpsc::Sender<session::Client> {
+    fn from_ref(state: &AppState) -> Self {
+        state.clients_tx.clone()
+    }
+}
+
 pub async fn start(
     listener: TcpListener,
+    command_tx: mpsc::Sender<Request>,
     clients_tx: mpsc::Sender<session::Client>,
+    auth: Auth,
+    tls: Option<Arc<ServerConfig>>,
+    interactive_preview: bool,
 ) -> Result<impl Future<Output = io::Result<()>>> {
     listener.set_nonblocking(true)?;
     let listener = tokio::net::TcpListener::from_std(listener)?;
     let addr = listener.local_addr().unwrap();
+    let scheme = if tls.is_some() { "https" } else { "http" };
     eprintln!("HTTP server listening on {addr}");
-    eprintln!("live preview available at http://{addr}");
 
-    let app: Router<()> = Router::new()
+    // the interactive preview needs the token allowing it to control the terminal
+    let token = if interactive_preview {
+        Some(auth.control_token())
+    } else {
+        auth.preview_token()
+    };
+
+    match token {
+        Some(token) => eprintln!(
+            "live preview available at {scheme}://{addr}/?token={}",
+            auth::encode(token)
+        ),
+        None => eprintln!("live preview available at {scheme}://{addr}"),
+    }
+
+    if auth.is_public_read() {
+        eprintln!("control API token: {}", auth.control_token());
+    }
+
+    let app = router(
+        AppState {
+            command_tx,
+            clients_tx,
+            interactive_preview,
+        },
+        auth,
+        tls.is_some(),
+    );
+
+    match tls {
+        Some(config) => Ok(Either::Right(serve_tls(listener, app, config))),
+
+        None => Ok(Either::Left(
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .into_future(),
+        )),
+    }
+}
+
+/// Builds the app, with `secure` telling whether it's served over TLS.
+fn router(state: AppState, auth: Auth, secure: bool) -> Router<()> {
+    // routes which control the terminal, requiring read-write access
+    let control = Router::new()
+        .route("/ws/control", get(control_handler))
+        .route("/api/input", post(input_handler))
+        .route("/api/keys", post(keys_handler))
+        .route("/api/resize", post(resize_handler))
+        .route_layer(middleware::from_fn(require_read_write));
+
+    Router::new()
         .route("/ws/alis", get(alis_handler))
         .route("/ws/events", get(event_stream_handler))
-        .with_state(clients_tx)
-        .fallback(static_handler);
+        .route("/snapshot.svg", get(snapshot_svg_handler))
+        .route("/snapshot.png", get(snapshot_png_handler))
+        .route("/snapshot.html", get(snapshot_html_handler))
+        .route("/api/snapshot", get(snapshot_handler))
+        .route("/api/status", get(status_handler))
+        .route("/api/preview", get(preview_handler))
+        .merge(control)
+        .with_state(state)
+        .fallback(static_handler)
+        .layer(middleware::from_fn_with_state((auth, secure), authenticate))
+        .layer(middleware::from_fn(check_origin))
+}
+
+/// Serves the app over TLS, doing what `axum::serve` does for plain TCP.
+async fn serve_tls(
+    listener: tokio::net::TcpListener,
+    app: Router<()>,
+    config: Arc<ServerConfig>,
+) -> io::Result<()> {
+    let acceptor = TlsAcceptor::from(config);
+    let mut make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(conn) => conn,
+
+            Err(e) if is_connection_error(&e) => continue,
+
+            // e.g. too many open files, which may get closed in a while
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let service = make_service.call(addr).await.unwrap_or_else(|e| match e {});
+
+        tokio::spawn(async move {
+            // failed handshakes, e.g. plain HTTP requests, and idle connections
+            // are dropped
+            let Ok(Ok(stream)) =
+                tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await
+            else {
+                return;
+            };
+
+            let _ = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), TowerToHyperService::new(service))
+                // upgrades needed for websockets
+                .with_upgrades()
+                .await;
+        });
+    }
+}
 
-    Ok(axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
     )
-    .into_future())
+}
+
+/// Checks the token of every request and records the granted access. Without
+/// a valid token, requests get read-only access if watching the terminal is
+/// public. A valid token passed in the query param is remembered in a cookie,
+/// so that pages opened with it can load their assets and connect to
+/// WebSocket endpoints. Over TLS, the cookie is never sent in plain text.
+async fn authenticate(
+    State((auth, secure)): State<(Auth, bool)>,
+    mut request: axum::extract::Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let header_value = |name| {
+        headers
+            .get(name)
+            .and_then(|v: &HeaderValue| v.to_str().ok())
+    };
+
+    let token = auth::find_token(
+        header_value(header::AUTHORIZATION),
+        request.uri().query(),
+        header_value(header::COOKIE),
+    );
+
+    let Some(access) = auth.access(token.as_ref().map(|(token, _)| token.as_ref())) else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "401",
+        )
+            .into_response();
+    };
+
+    // only tokens which grant more than anonymous requests get are remembered
+    let cookie = token
+        .filter(|(token, from_query)| *from_query && auth.access(Some(token)) != auth.access(None))
+        .map(|(token, _)| {
+            format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Strict{}",
+                auth::COOKIE_NAME,
+                auth::encode(&token),
+                if secure { "; Secure" } else { "" }
+            )
+        });
+
+    request.extensions_mut().insert(access);
+    let mut response = next.run(request).await;
+
+    if let Some(cookie) = cookie.and_then(|c| HeaderValue::from_str(&c).ok()) {
+        response.headers_mut().append(header::SET_COOKIE, cookie);
+    }
+
+    response
+}
+
+/// Rejects requests made by other web pages open in the browser, which would
+/// otherwise be able to watch and control the terminal, e.g. by connecting to
+/// `/ws/control`. Requests without `Origin` header don't come from web pages.
+async fn check_origin(request: axum::extract::Request, next: Next) -> Response {
+    let headers = request.headers();
+    let origin = headers
+        .get(header::ORIGIN)
+        .map(|v| v.to_str().unwrap_or(""));
+    let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+
+    match (origin, host) {
+        (None, _) => next.run(request).await,
+        (Some(origin), Some(host)) if auth::same_origin(origin, host) => next.run(request).await,
+        _ => (StatusCode::FORBIDDEN, "403").into_response(),
+    }
+}
+
+async fn require_read_write(request: axum::extract::Request, next: Next) -> Response {
+    match request.extensions().get::<Access>() {
+        Some(Access::ReadWrite) => next.run(request).await,
+        _ => (StatusCode::FORBIDDEN, "403").into_response(),
+    }
 }
 
 /// ALiS protocol handler
@@ -67,7 +294,7 @@ async fn handle_alis_socket(
     let (sink, stream) = socket.split();
     let drainer = tokio::spawn(stream.map(Ok).forward(sink::drain()));
 
-    let result = session::stream(&clients_tx)
+    let result = session::stream(&clients_tx, None)
         .await?
         .filter_map(alis_message)
         .chain(stream::once(future::ready(Ok(close_message()))))
@@ -86,22 +313,50 @@ async fn alis_message(
     use session::Event::*;
 
     match event {
-        Ok(Init(time, cols, rows, _pid, seq, _text)) => Some(Ok(json_message(json!({
-            "time": time,
-            "cols": cols,
-            "rows": rows,
-            "init": seq,
-        })))),
+        Ok(Init(time, cols, rows, _pid, seq, _text, _cursor, _modes, _driver)) => {
+            Some(Ok(json_message(json!({
+                "time": time,
+                "cols": cols,
+                "rows": rows,
+                "init": seq,
+            }))))
+        }
 
         Ok(Output(time, data)) => Some(Ok(json_message(json!([time, "o", data])))),
 
+        Ok(Input(_, _)) => None,
+
         Ok(Resize(time, cols, rows)) => Some(Ok(json_message(json!([
             time,
             "r",
             format!("{cols}x{rows}")
         ])))),
 
-        Ok(Snapshot(_, _, _, _)) => None,
+        Ok(Snapshot(_, _, _, _, _, _, _, _)) => None,
+
+        Ok(WaitResult(_, _)) => None,
+
+        Ok(Idle) => None,
+
+        Ok(Ack(_)) => None,
+
+        Ok(Error(_, _)) => None,
+
+        Ok(Scrollback(_, _, _, _, _)) => None,
+
+        Ok(View(_, _, _)) => None,
+
+        Ok(Exit(_)) => None,
+
+        Ok(Title(_)) => None,
+
+        Ok(Bell) => None,
+
+        Ok(Notification(_, _)) => None,
+
+        Ok(Screenshot(_, _, _, _)) => None,
+
+        Ok(Control(_)) => None,
 
         Err(e) => Some(Err(axum::Error::new(e))),
     }
@@ -112,6 +367,12 @@ struct EventsParams {
     sub: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct SnapshotParams {
+    #[serde(default)]
+    format: render::Format,
+}
+
 /// Event stream handler
 ///
 /// This endpoint allows the client to subscribe to selected events and have them delivered as they occur.
@@ -138,7 +399,7 @@ async fn handle_event_stream_socket(
     let (sink, stream) = socket.split();
     let drainer = tokio::spawn(stream.map(Ok).forward(sink::drain()));
 
-    let result = session::stream(&clients_tx)
+    let result = session::stream(&clients_tx, None)
         .await?
         .filter_map(move |e| event_stream_message(e, sub))
         .chain(stream::once(future::ready(Ok(close_message()))))
@@ -155,18 +416,290 @@ async fn event_stream_message(
     event: Result<session::Event, BroadcastStreamRecvError>,
     sub: Subscription,
 ) -> Option<Result<ws::Message, axum::Error>> {
-    use session::Event::*;
-
     match event {
-        Ok(e @ Init(_, _, _, _, _, _)) if sub.init => Some(Ok(json_message(e.to_json()))),
-        Ok(e @ Output(_, _)) if sub.output => Some(Ok(json_message(e.to_json()))),
-        Ok(e @ Resize(_, _, _)) if sub.resize => Some(Ok(json_message(e.to_json()))),
-        Ok(e @ Snapshot(_, _, _, _)) if sub.snapshot => Some(Ok(json_message(e.to_json()))),
+        Ok(e) if sub.includes(&e) => Some(Ok(json_message(e.to_json()))),
         Ok(_) => None,
         Err(e) => Some(Err(axum::Error::new(e))),
     }
 }
 
+/// Control handler
+///
+/// This endpoint accepts the same JSON commands as STDIO API, one per text
+/// message, and delivers the events selected with `sub` query param, same as
+/// the event stream endpoint.
+async fn control_handler(
+    ws: ws::WebSocketUpgrade,
+    Query(params): Query<EventsParams>,
+    ConnectInfo(_addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let sub: Subscription = params.sub.unwrap_or_default().parse().unwrap_or_default();
+
+    ws.on_upgrade(move |socket| async move {
+        let client = ClientId::next();
+        let command_tx = state.command_tx;
+        let _ =
+            handle_control_socket(socket, command_tx.clone(), state.clients_tx, sub, client).await;
+
+        // the control taken by the connection ends with it
+        let _ = command_tx.send(Request::release_control(client)).await;
+    })
+}
+
+async fn handle_control_socket(
+    socket: ws::WebSocket,
+    command_tx: mpsc::Sender<Request>,
+    clients_tx: mpsc::Sender<session::Client>,
+    sub: Subscription,
+    client: ClientId,
+) -> Result<()> {
+    let (mut sink, mut stream) = socket.split();
+    let mut events = session::stream(&clients_tx, Some(client)).await?;
+
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                match message {
+                    Some(Ok(ws::Message::Text(line))) => {
+                        match parser::parse_line(&line) {
+                            Ok(request) => command_tx.send(Request { client, ..request }).await?,
+
+                            Err((id, e)) => {
+                                if sub.reports_parse_error(&id) {
+                                    sink.send(json_message(session::Event::Error(Reply { client, id }, e).to_json())).await?;
+                                }
+                            }
+                        }
+                    }
+
+                    Some(Ok(ws::Message::Close(_))) => {
+                        // flushes the automatic reply to the client's close frame
+                        let _ = sink.close().await;
+                        break;
+                    }
+
+                    Some(Err(_)) | None => break,
+
+                    Some(Ok(_)) => (),
+                }
+            }
+
+            event = events.next() => {
+                match event {
+                    Some(event) => {
+                        if let Some(message) = event_stream_message(event, sub).await {
+                            sink.send(message?).await?;
+                        }
+                    }
+
+                    None => {
+                        sink.send(close_message()).await?;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// SVG snapshot handler
+///
+/// This endpoint renders the current terminal view as an SVG image.
+async fn snapshot_svg_handler(State(clients_tx): State<mpsc::Sender<session::Client>>) -> Response {
+    image_response(&clients_tx, render::Image::Svg).await
+}
+
+/// PNG snapshot handler
+///
+/// This endpoint renders the current terminal view as a PNG image.
+async fn snapshot_png_handler(State(clients_tx): State<mpsc::Sender<session::Client>>) -> Response {
+    image_response(&clients_tx, render::Image::Png).await
+}
+
+/// HTML snapshot handler
+///
+/// This endpoint renders the current terminal view as an HTML document.
+async fn snapshot_html_handler(
+    State(clients_tx): State<mpsc::Sender<session::Client>>,
+) -> Response {
+    image_response(&clients_tx, render::Image::Html).await
+}
+
+/// Input handler
+///
+/// This endpoint sends raw input to the process, same as the `input` command.
+/// Request body: `{ "payload": "..." }`.
+async fn input_handler(
+    State(state): State<AppState>,
+    args: Result<Json<serde_json::Map<String, serde_json::Value>>, JsonRejection>,
+) -> Response {
+    execute(&state, build_command("input", args)).await
+}
+
+/// Keys handler
+///
+/// This endpoint sends keys to the process, same as the `sendKeys` command.
+/// Request body: `{ "keys": ["..."] }`.
+async fn keys_handler(
+    State(state): State<AppState>,
+    args: Result<Json<serde_json::Map<String, serde_json::Value>>, JsonRejection>,
+) -> Response {
+    execute(&state, build_command("sendKeys", args)).await
+}
+
+/// Resize handler
+///
+/// This endpoint resizes the terminal, same as the `resize` command.
+/// Request body: `{ "cols": 80, "rows": 24 }`.
+async fn resize_handler(
+    State(state): State<AppState>,
+    args: Result<Json<serde_json::Map<String, serde_json::Value>>, JsonRejection>,
+) -> Response {
+    execute(&state, build_command("resize", args)).await
+}
+
+/// Snapshot handler
+///
+/// This endpoint takes a snapshot of the terminal, same as the
+/// `takeSnapshot` command. Query param `format` selects the kind of snapshot.
+async fn snapshot_handler(
+    State(state): State<AppState>,
+    params: Result<Query<SnapshotParams>, QueryRejection>,
+) -> Response {
+    let command = params
+        .map(|Query(params)| Command::Snapshot(params.format))
+        .map_err(|e| e.body_text());
+
+    execute(&state, command).await
+}
+
+/// Status handler
+///
+/// This endpoint returns the size of the terminal, the pid of the process,
+/// the cursor and the terminal modes.
+async fn status_handler(State(state): State<AppState>) -> Response {
+    let Ok(mut events) = session::stream(&state.clients_tx, None).await else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "session ended");
+    };
+
+    match events.next().await {
+        Some(Ok(e @ session::Event::Init(_, _, _, _, _, _, _, _, _))) => {
+            let mut data = e.to_json()["data"].take();
+
+            if let Some(data) = data.as_object_mut() {
+                data.remove("seq");
+                data.remove("text");
+            }
+
+            Json(data).into_response()
+        }
+
+        _ => error_response(StatusCode::SERVICE_UNAVAILABLE, "session ended"),
+    }
+}
+
+/// Preview handler
+///
+/// This endpoint tells the live preview page whether it should let the user
+/// type into the terminal.
+async fn preview_handler(State(state): State<AppState>) -> Response {
+    Json(json!({ "interactive": state.interactive_preview })).into_response()
+}
+
+/// Builds the command from the JSON body of a REST request, the same way
+/// STDIO API does.
+fn build_command(
+    command_type: &str,
+    args: Result<Json<serde_json::Map<String, serde_json::Value>>, JsonRejection>,
+) -> Result<Command, String> {
+    let Json(mut args) = args.map_err(|e| e.body_text())?;
+    args.insert("type".to_owned(), json!(command_type));
+
+    parser::build_command(serde_json::Value::Object(args))
+}
+
+/// Sends the command of a REST request to the session and waits for its
+/// result.
+async fn execute(state: &AppState, command: Result<Command, String>) -> Response {
+    let command = match command {
+        Ok(command) => command,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let id = json!(format!("http-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+
+    // REST requests can't take control of the terminal, so each is a client
+    // of its own
+    let client = ClientId::next();
+
+    // subscribing before sending the command makes sure its result isn't missed
+    let Ok(mut events) = session::stream(&state.clients_tx, Some(client)).await else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "session ended");
+    };
+
+    let request = Request {
+        id: Some(id.clone()),
+        command,
+        client,
+    };
+
+    if state.command_tx.send(request).await.is_err() {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "session ended");
+    }
+
+    let result = tokio::time::timeout(RESULT_TIMEOUT, async {
+        while let Some(event) = events.next().await {
+            use session::Event::*;
+
+            let Ok(event) = event else {
+                continue;
+            };
+
+            match &event {
+                Ack(reply) if reply.id.as_ref() == Some(&id) => {
+                    return StatusCode::NO_CONTENT.into_response();
+                }
+
+                Error(reply, message) if reply.id.as_ref() == Some(&id) => {
+                    return error_response(StatusCode::CONFLICT, message);
+                }
+
+                Snapshot(Some(reply), _, _, _, _, _, _, _) if reply.id.as_ref() == Some(&id) => {
+                    return Json(event.to_json()["data"].take()).into_response();
+                }
+
+                _ => (),
+            }
+        }
+
+        error_response(StatusCode::SERVICE_UNAVAILABLE, "session ended")
+    })
+    .await;
+
+    result.unwrap_or_else(|_| {
+        error_response(StatusCode::GATEWAY_TIMEOUT, "timeout waiting for result")
+    })
+}
+
+fn error_response<S: ToString>(status: StatusCode, message: S) -> Response {
+    (status, Json(json!({ "error": message.to_string() }))).into_response()
+}
+
+/// Has the session render its current view, without subscribing to its
+/// events.
+async fn image_response(
+    clients_tx: &mpsc::Sender<session::Client>,
+    image: render::Image,
+) -> Response {
+    match session::render_image(clients_tx, image).await {
+        Ok(data) => ([(header::CONTENT_TYPE, image.content_type())], data).into_response(),
+        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "503").into_response(),
+    }
+}
+
 fn json_message(value: serde_json::Value) -> ws::Message {
     ws::Message::Text(value.to_string())
 }
@@ -195,3 +728,313 @@ async fn static_handler(uri: Uri) -> impl IntoResponse {
         None => (StatusCode::NOT_FOUND, "404").into_response(),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{router, serve_tls, AppState, Auth};
+    use crate::api::tls;
+    use crate::command::{ClientId, Command, Reply, Request};
+    use crate::render::theme::Theme;
+    use crate::session::Session;
+    use axum::body::Body;
+    use axum::http::{header, Method, StatusCode};
+    use axum::Router;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::sync::mpsc;
+    use tower_service::Service;
+
+    /// Runs a session which acks input, errors on resize, takes snapshots and
+    /// ignores other commands, unless `respond` is false, in which case it
+    /// ignores all of them.
+    fn start_session(respond: bool) -> AppState {
+        let (command_tx, mut command_rx) = mpsc::channel::<Request>(16);
+        let (clients_tx, mut clients_rx) = mpsc::channel(1);
+
+        tokio::spawn(async move {
+            let mut session = Session::new(8, 3, 1, Duration::from_secs(1), 100, Theme::default());
+
+            loop {
+                tokio::select! {
+                    request = command_rx.recv() => {
+                        match request {
+                            Some(Request { id, command: Command::Input(_), client }) if respond => {
+                                // results for other clients are ignored, even
+                                // with the same id
+                                let other = ClientId::next();
+                                session.error(Reply { client: other, id: id.clone() }, "not this one");
+                                session.ack(Reply { client, id });
+                            }
+
+                            Some(Request { id, command: Command::Resize(_, _), client }) if respond => {
+                                session.error(Reply { client, id }, "cannot resize");
+                            }
+
+                            Some(Request { id, command: Command::Snapshot(format), client }) if respond => {
+                                session.output("hello".to_owned());
+                                session.snapshot(Some(Reply { client, id }), format);
+                            }
+
+                            Some(_) => (),
+
+                            None => break,
+                        }
+                    }
+
+                    client = clients_rx.recv() => {
+                        match client {
+                            Some(client) => session.serve(client),
+                            None => break,
+                        }
+                    }
+                }
+            }
+        });
+
+        AppState {
+            command_tx,
+            clients_tx,
+            interactive_preview: false,
+        }
+    }
+
+    fn public_auth() -> Auth {
+        Auth::new("rw".to_owned(), None, true)
+    }
+
+    async fn request(
+        app: &mut Router,
+        method: Method,
+        uri: &str,
+        body: &str,
+        token: Option<&str>,
+    ) -> (StatusCode, Value) {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::HOST, "127.0.0.1:8080")
+            .header(header::CONTENT_TYPE, "application/json");
+
+        send(app, request, body, token).await
+    }
+
+    async fn send(
+        app: &mut Router,
+        mut request: axum::http::request::Builder,
+        body: &str,
+        token: Option<&str>,
+    ) -> (StatusCode, Value) {
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        let request = request.body(Body::from(body.to_owned())).unwrap();
+        let response = app.call(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn status() {
+        let mut app = router(start_session(true), public_auth(), false);
+
+        let (status, body) = request(&mut app, Method::GET, "/api/status", "", None).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            (body["cols"].clone(), body["rows"].clone()),
+            (json!(8), json!(3))
+        );
+        assert_eq!(body["pid"], json!(1));
+        assert!(body.get("seq").is_none());
+    }
+
+    #[tokio::test]
+    async fn input() {
+        let mut app = router(start_session(true), public_auth(), false);
+
+        let (status, _) = request(
+            &mut app,
+            Method::POST,
+            "/api/input",
+            r#"{ "payload": "ls\r" }"#,
+            Some("rw"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (status, body) = request(
+            &mut app,
+            Method::POST,
+            "/api/keys",
+            r#"{ "keys": "C-c" }"#,
+            Some("rw"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+
+        let (status, body) =
+            request(&mut app, Method::POST, "/api/input", "nope", Some("rw")).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn resize_error() {
+        let mut app = router(start_session(true), public_auth(), false);
+
+        let (status, body) = request(
+            &mut app,
+            Method::POST,
+            "/api/resize",
+            r#"{ "cols": 100, "rows": 30 }"#,
+            Some("rw"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body, json!({ "error": "cannot resize" }));
+    }
+
+    #[tokio::test]
+    async fn snapshot() {
+        let mut app = router(start_session(true), public_auth(), false);
+
+        let (status, body) = request(&mut app, Method::GET, "/api/snapshot", "", None).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["text"], json!("hello   \n        \n        "));
+
+        let uri = "/api/snapshot?format=html";
+        let (status, body) = request(&mut app, Method::GET, uri, "", None).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["html"].as_str().unwrap().starts_with("<pre"));
+
+        let uri = "/api/snapshot?format=nope";
+        let (status, body) = request(&mut app, Method::GET, uri, "", None).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout() {
+        let mut app = router(start_session(false), public_auth(), false);
+
+        let (status, body) = request(
+            &mut app,
+            Method::POST,
+            "/api/input",
+            r#"{ "payload": "x" }"#,
+            Some("rw"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(body, json!({ "error": "timeout waiting for result" }));
+    }
+
+    #[tokio::test]
+    async fn access() {
+        let auth = Auth::new("rw".to_owned(), Some("ro".to_owned()), false);
+        let mut app = router(start_session(true), auth, false);
+        let body = r#"{ "payload": "x" }"#;
+
+        let (status, _) = request(&mut app, Method::POST, "/api/input", body, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = request(&mut app, Method::GET, "/api/status", "", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = request(&mut app, Method::POST, "/api/input", body, Some("ro")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = request(&mut app, Method::GET, "/api/status", "", Some("ro")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = request(&mut app, Method::POST, "/api/input", body, Some("rw")).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn public_read() {
+        let mut app = router(start_session(true), public_auth(), false);
+        let body = r#"{ "payload": "x" }"#;
+
+        let (status, _) = request(&mut app, Method::GET, "/api/status", "", None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = request(&mut app, Method::POST, "/api/input", body, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = request(&mut app, Method::POST, "/api/input", body, Some("ro")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn origin() {
+        let mut app = router(start_session(true), public_auth(), false);
+
+        let request = |origin| {
+            axum::http::Request::builder()
+                .uri("/api/status")
+                .header(header::HOST, "127.0.0.1:8080")
+                .header(header::ORIGIN, origin)
+        };
+
+        let (status, _) = send(&mut app, request("http://127.0.0.1:8080"), "", None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(&mut app, request("https://evil.example"), "", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&mut app, request("https://evil.example"), "", Some("rw")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn cookie() {
+        let auth = Auth::new("rw".to_owned(), Some("ro".to_owned()), false);
+
+        for secure in [false, true] {
+            let mut app = router(start_session(true), auth.clone(), secure);
+
+            let request = axum::http::Request::builder()
+                .uri("/api/status?token=ro")
+                .header(header::HOST, "127.0.0.1:8080")
+                .body(Body::empty())
+                .unwrap();
+
+            let response = app.call(request).await.unwrap();
+            let cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+
+            assert!(cookie.starts_with("ht_token=ro; Path=/; HttpOnly; SameSite=Strict"));
+            assert_eq!(cookie.ends_with("; Secure"), secure);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tls_handshake_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(start_session(true), public_auth(), false);
+        tokio::spawn(serve_tls(listener, app, tls::self_signed(None).unwrap()));
+
+        // a client which never starts the handshake gets disconnected
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0; 1];
+        let read = tokio::time::timeout(Duration::from_secs(60), stream.read(&mut buf)).await;
+
+        assert_eq!(read.expect("connection should be closed").unwrap(), 0);
+    }
+}
@@ -0,0 +1,890 @@
+use crate::command::{
+    ClientId, Command, Id, InputSeq, Modifiers, Mouse, MouseAction, MouseButton, Request,
+    ScrollDirection,
+};
+use crate::pty::SignalTarget;
+use crate::render;
+use crate::wait::{Pattern, Region, Wait};
+use nix::sys::signal::Signal;
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+struct InputArgs {
+    payload: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SendKeysArgs {
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResizeArgs {
+    cols: usize,
+    rows: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct TakeSnapshotArgs {
+    #[serde(default)]
+    format: render::Format,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeekArgs {
+    time: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PasteArgs {
+    payload: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MouseArgs {
+    action: String,
+    button: Option<MouseButton>,
+    direction: Option<ScrollDirection>,
+    col: usize,
+    row: usize,
+    #[serde(default)]
+    shift: bool,
+    #[serde(default)]
+    alt: bool,
+    #[serde(default)]
+    ctrl: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignalArgs {
+    name: String,
+    #[serde(default)]
+    target: SignalTarget,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetScrollbackArgs {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+    #[serde(default)]
+    format: render::Format,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaitForArgs {
+    text: Option<String>,
+    regex: Option<String>,
+    region: Option<Region>,
+    timeout: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaitForIdleArgs {
+    ms: u64,
+    timeout: Option<u64>,
+}
+
+/// Parses a JSON encoded command, as sent by STDIO and WebSocket clients.
+pub fn parse_line(line: &str) -> Result<Request, (Option<Id>, String)> {
+    let value =
+        serde_json::from_str::<serde_json::Value>(line).map_err(|e| (None, e.to_string()))?;
+    let id = value.get("id").cloned();
+
+    match build_command(value) {
+        Ok(command) => Ok(Request {
+            id,
+            command,
+            client: ClientId::default(),
+        }),
+        Err(e) => Err((id, e)),
+    }
+}
+
+/// Builds a command from a JSON object with its `type` and arguments.
+pub fn build_command(value: serde_json::Value) -> Result<Command, String> {
+    match value["type"].as_str() {
+        Some("input") => {
+            let args: InputArgs = args_from_json_value(value)?;
+            Ok(Command::Input(vec![standard_key(args.payload)]))
+        }
+
+        Some("sendKeys") => {
+            let args: SendKeysArgs = args_from_json_value(value)?;
+            let seqs = args.keys.into_iter().map(parse_key).collect();
+            Ok(Command::Input(seqs))
+        }
+
+        Some("resize") => {
+            let args: ResizeArgs = args_from_json_value(value)?;
+            let range = 1..=u16::MAX as usize;
+
+            if !range.contains(&args.cols) || !range.contains(&args.rows) {
+                return Err(format!(
+                    "`cols` and `rows` must be between 1 and {}",
+                    u16::MAX
+                ));
+            }
+
+            Ok(Command::Resize(args.cols, args.rows))
+        }
+
+        Some("takeSnapshot") => {
+            let args: TakeSnapshotArgs = args_from_json_value(value)?;
+            Ok(Command::Snapshot(args.format))
+        }
+
+        Some("takeScreenshot") => Ok(Command::Screenshot),
+
+        Some("waitFor") => {
+            let args: WaitForArgs = args_from_json_value(value)?;
+
+            let pattern = match (args.text, args.regex) {
+                (Some(text), None) if text.is_empty() => {
+                    return Err("`text` must not be empty".to_owned())
+                }
+
+                (Some(text), None) => Pattern::Text(text),
+                (None, Some(re)) => Pattern::Regex(Regex::new(&re).map_err(|e| e.to_string())?),
+                _ => return Err("exactly one of `text` or `regex` is required".to_owned()),
+            };
+
+            if args.region.is_some_and(|r| r.cols == 0 || r.rows == 0) {
+                return Err("`region` must not be empty".to_owned());
+            }
+
+            let timeout = args.timeout.map(Duration::from_millis);
+
+            Ok(Command::WaitFor(Wait::new(pattern, args.region, timeout)))
+        }
+
+        Some("waitForIdle") => {
+            let args: WaitForIdleArgs = args_from_json_value(value)?;
+            let period = Duration::from_millis(args.ms);
+            let timeout = args.timeout.map(Duration::from_millis);
+
+            Ok(Command::WaitFor(Wait::idle(period, timeout)))
+        }
+
+        Some("restart") => Ok(Command::Restart),
+
+        Some("takeControl") => Ok(Command::TakeControl),
+
+        Some("releaseControl") => Ok(Command::ReleaseControl),
+
+        Some("seek") => {
+            let args: SeekArgs = args_from_json_value(value)?;
+            Ok(Command::Seek(args.time))
+        }
+
+        Some("paste") => {
+            let args: PasteArgs = args_from_json_value(value)?;
+            Ok(Command::Paste(args.payload))
+        }
+
+        Some("mouse") => {
+            let args: MouseArgs = args_from_json_value(value)?;
+            Ok(Command::Mouse(parse_mouse(args)?))
+        }
+
+        Some("signal") => {
+            let args: SignalArgs = args_from_json_value(value)?;
+            Ok(Command::Signal(parse_signal(&args.name)?, args.target))
+        }
+
+        Some("getScrollback") => {
+            let args: GetScrollbackArgs = args_from_json_value(value)?;
+            Ok(Command::GetScrollback(args.offset, args.limit, args.format))
+        }
+
+        other => Err(format!("invalid command type: {other:?}")),
+    }
+}
+
+fn args_from_json_value<T>(value: serde_json::Value) -> Result<T, String>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+fn parse_mouse(args: MouseArgs) -> Result<Vec<Mouse>, String> {
+    let button = args.button.unwrap_or(MouseButton::Left);
+
+    let actions = match args.action.as_str() {
+        "press" => vec![MouseAction::Press(button)],
+        "release" => vec![MouseAction::Release(button)],
+        "click" => vec![MouseAction::Press(button), MouseAction::Release(button)],
+        "move" => vec![MouseAction::Move(args.button)],
+
+        "scroll" => {
+            let direction = args.direction.ok_or("`direction` is required for scroll")?;
+            vec![MouseAction::Scroll(direction)]
+        }
+
+        other => return Err(format!("invalid mouse action: {other}")),
+    };
+
+    let modifiers = Modifiers {
+        shift: args.shift,
+        alt: args.alt,
+        ctrl: args.ctrl,
+    };
+
+    Ok(actions
+        .into_iter()
+        .map(|action| Mouse {
+            action,
+            col: args.col,
+            row: args.row,
+            modifiers,
+        })
+        .collect())
+}
+
+fn parse_signal(name: &str) -> Result<Signal, String> {
+    let name = name.to_uppercase();
+
+    if name.starts_with("SIG") {
+        Signal::from_str(&name)
+    } else {
+        Signal::from_str(&format!("SIG{name}"))
+    }
+    .map_err(|_| format!("invalid signal name: {name}"))
+}
+
+fn standard_key<S: ToString>(seq: S) -> InputSeq {
+    InputSeq::Standard(seq.to_string())
+}
+
+fn cursor_key<S: ToString>(seq1: S, seq2: S) -> InputSeq {
+    InputSeq::Cursor(seq1.to_string(), seq2.to_string())
+}
+
+fn parse_key(key: String) -> InputSeq {
+    let seq = match key.as_str() {
+        "C-@" | "C-Space" | "^@" => "\x00",
+        "C-[" | "Escape" | "^[" => "\x1b",
+        "C-\\" | "^\\" => "\x1c",
+        "C-]" | "^]" => "\x1d",
+        "C-^" | "C-/" => "\x1e",
+        "C--" | "C-_" => "\x1f",
+        "Tab" => "\x09",       // same as C-i
+        "Enter" => "\x0d",     // same as C-m
+        "Backspace" => "\x7f", // DEL character
+        "Space" => " ",
+        "Left" => return cursor_key("\x1b[D", "\x1bOD"),
+        "Right" => return cursor_key("\x1b[C", "\x1bOC"),
+        "Up" => return cursor_key("\x1b[A", "\x1bOA"),
+        "Down" => return cursor_key("\x1b[B", "\x1bOB"),
+        "C-Left" => "\x1b[1;5D",
+        "C-Right" => "\x1b[1;5C",
+        "S-Left" => "\x1b[1;2D",
+        "S-Right" => "\x1b[1;2C",
+        "C-Up" => "\x1b[1;5A",
+        "C-Down" => "\x1b[1;5B",
+        "S-Up" => "\x1b[1;2A",
+        "S-Down" => "\x1b[1;2B",
+        "A-Left" => "\x1b[1;3D",
+        "A-Right" => "\x1b[1;3C",
+        "A-Up" => "\x1b[1;3A",
+        "A-Down" => "\x1b[1;3B",
+        "C-S-Left" | "S-C-Left" => "\x1b[1;6D",
+        "C-S-Right" | "S-C-Right" => "\x1b[1;6C",
+        "C-S-Up" | "S-C-Up" => "\x1b[1;6A",
+        "C-S-Down" | "S-C-Down" => "\x1b[1;6B",
+        "C-A-Left" | "A-C-Left" => "\x1b[1;7D",
+        "C-A-Right" | "A-C-Right" => "\x1b[1;7C",
+        "C-A-Up" | "A-C-Up" => "\x1b[1;7A",
+        "C-A-Down" | "A-C-Down" => "\x1b[1;7B",
+        "A-S-Left" | "S-A-Left" => "\x1b[1;4D",
+        "A-S-Right" | "S-A-Right" => "\x1b[1;4C",
+        "A-S-Up" | "S-A-Up" => "\x1b[1;4A",
+        "A-S-Down" | "S-A-Down" => "\x1b[1;4B",
+        "C-A-S-Left" | "C-S-A-Left" | "A-C-S-Left" | "S-C-A-Left" | "A-S-C-Left" | "S-A-C-Left" => {
+            "\x1b[1;8D"
+        }
+        "C-A-S-Right" | "C-S-A-Right" | "A-C-S-Right" | "S-C-A-Right" | "A-S-C-Right"
+        | "S-A-C-Right" => "\x1b[1;8C",
+        "C-A-S-Up" | "C-S-A-Up" | "A-C-S-Up" | "S-C-A-Up" | "A-S-C-Up" | "S-A-C-Up" => "\x1b[1;8A",
+        "C-A-S-Down" | "C-S-A-Down" | "A-C-S-Down" | "S-C-A-Down" | "A-S-C-Down" | "S-A-C-Down" => {
+            "\x1b[1;8B"
+        }
+        "F1" => "\x1bOP",
+        "F2" => "\x1bOQ",
+        "F3" => "\x1bOR",
+        "F4" => "\x1bOS",
+        "F5" => "\x1b[15~",
+        "F6" => "\x1b[17~",
+        "F7" => "\x1b[18~",
+        "F8" => "\x1b[19~",
+        "F9" => "\x1b[20~",
+        "F10" => "\x1b[21~",
+        "F11" => "\x1b[23~",
+        "F12" => "\x1b[24~",
+        "C-F1" => "\x1b[1;5P",
+        "C-F2" => "\x1b[1;5Q",
+        "C-F3" => "\x1b[1;5R",
+        "C-F4" => "\x1b[1;5S",
+        "C-F5" => "\x1b[15;5~",
+        "C-F6" => "\x1b[17;5~",
+        "C-F7" => "\x1b[18;5~",
+        "C-F8" => "\x1b[19;5~",
+        "C-F9" => "\x1b[20;5~",
+        "C-F10" => "\x1b[21;5~",
+        "C-F11" => "\x1b[23;5~",
+        "C-F12" => "\x1b[24;5~",
+        "S-F1" => "\x1b[1;2P",
+        "S-F2" => "\x1b[1;2Q",
+        "S-F3" => "\x1b[1;2R",
+        "S-F4" => "\x1b[1;2S",
+        "S-F5" => "\x1b[15;2~",
+        "S-F6" => "\x1b[17;2~",
+        "S-F7" => "\x1b[18;2~",
+        "S-F8" => "\x1b[19;2~",
+        "S-F9" => "\x1b[20;2~",
+        "S-F10" => "\x1b[21;2~",
+        "S-F11" => "\x1b[23;2~",
+        "S-F12" => "\x1b[24;2~",
+        "A-F1" => "\x1b[1;3P",
+        "A-F2" => "\x1b[1;3Q",
+        "A-F3" => "\x1b[1;3R",
+        "A-F4" => "\x1b[1;3S",
+        "A-F5" => "\x1b[15;3~",
+        "A-F6" => "\x1b[17;3~",
+        "A-F7" => "\x1b[18;3~",
+        "A-F8" => "\x1b[19;3~",
+        "A-F9" => "\x1b[20;3~",
+        "A-F10" => "\x1b[21;3~",
+        "A-F11" => "\x1b[23;3~",
+        "A-F12" => "\x1b[24;3~",
+        "Home" => return cursor_key("\x1b[H", "\x1bOH"),
+        "C-Home" => "\x1b[1;5H",
+        "S-Home" => "\x1b[1;2H",
+        "A-Home" => "\x1b[1;3H",
+        "End" => return cursor_key("\x1b[F", "\x1bOF"),
+        "C-End" => "\x1b[1;5F",
+        "S-End" => "\x1b[1;2F",
+        "A-End" => "\x1b[1;3F",
+        "PageUp" => "\x1b[5~",
+        "C-PageUp" => "\x1b[5;5~",
+        "S-PageUp" => "\x1b[5;2~",
+        "A-PageUp" => "\x1b[5;3~",
+        "PageDown" => "\x1b[6~",
+        "C-PageDown" => "\x1b[6;5~",
+        "S-PageDown" => "\x1b[6;2~",
+        "A-PageDown" => "\x1b[6;3~",
+        "Insert" => "\x1b[2~",
+        "C-Insert" => "\x1b[2;5~",
+        "S-Insert" => "\x1b[2;2~",
+        "A-Insert" => "\x1b[2;3~",
+        "Delete" => "\x1b[3~",
+        "C-Delete" => "\x1b[3;5~",
+        "S-Delete" => "\x1b[3;2~",
+        "A-Delete" => "\x1b[3;3~",
+        "S-Tab" => "\x1b[Z",
+
+        k => {
+            let chars: Vec<char> = k.chars().collect();
+
+            match chars.as_slice() {
+                ['C', '-', k @ 'a'..='z'] => {
+                    return standard_key((*k as u8 - 0x60) as char);
+                }
+
+                ['C', '-', k @ 'A'..='Z'] => {
+                    return standard_key((*k as u8 - 0x40) as char);
+                }
+
+                ['^', k @ 'a'..='z'] => {
+                    return standard_key((*k as u8 - 0x60) as char);
+                }
+
+                ['^', k @ 'A'..='Z'] => {
+                    return standard_key((*k as u8 - 0x40) as char);
+                }
+
+                ['A', '-', k] => {
+                    return standard_key(format!("\x1b{}", k));
+                }
+
+                _ => &key,
+            }
+        }
+    };
+
+    standard_key(seq)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cursor_key, parse_line, standard_key, Command};
+    use crate::command::{InputSeq, MouseAction, MouseButton, ScrollDirection};
+    use crate::pty::SignalTarget;
+    use crate::render::Format;
+    use nix::sys::signal::Signal;
+    use serde_json::json;
+
+    #[test]
+    fn parse_input() {
+        let command = parse_line(r#"{ "type": "input", "payload": "hello" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Input(input) if input == vec![standard_key("hello")]));
+    }
+
+    #[test]
+    fn parse_input_missing_args() {
+        parse_line(r#"{ "type": "input" }"#).expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_send_keys() {
+        let examples = [
+            ["hello", "hello"],
+            ["C-@", "\x00"],
+            ["C-a", "\x01"],
+            ["C-A", "\x01"],
+            ["^a", "\x01"],
+            ["^A", "\x01"],
+            ["C-z", "\x1a"],
+            ["C-Z", "\x1a"],
+            ["C-[", "\x1b"],
+            ["Space", " "],
+            ["C-Space", "\x00"],
+            ["Tab", "\x09"],
+            ["Enter", "\x0d"],
+            ["Backspace", "\x7f"],
+            ["Escape", "\x1b"],
+            ["^[", "\x1b"],
+            ["C-Left", "\x1b[1;5D"],
+            ["C-Right", "\x1b[1;5C"],
+            ["S-Left", "\x1b[1;2D"],
+            ["S-Right", "\x1b[1;2C"],
+            ["C-Up", "\x1b[1;5A"],
+            ["C-Down", "\x1b[1;5B"],
+            ["S-Up", "\x1b[1;2A"],
+            ["S-Down", "\x1b[1;2B"],
+            ["A-Left", "\x1b[1;3D"],
+            ["A-Right", "\x1b[1;3C"],
+            ["A-Up", "\x1b[1;3A"],
+            ["A-Down", "\x1b[1;3B"],
+            ["C-S-Left", "\x1b[1;6D"],
+            ["C-S-Right", "\x1b[1;6C"],
+            ["C-S-Up", "\x1b[1;6A"],
+            ["C-S-Down", "\x1b[1;6B"],
+            ["C-A-Left", "\x1b[1;7D"],
+            ["C-A-Right", "\x1b[1;7C"],
+            ["C-A-Up", "\x1b[1;7A"],
+            ["C-A-Down", "\x1b[1;7B"],
+            ["S-A-Left", "\x1b[1;4D"],
+            ["S-A-Right", "\x1b[1;4C"],
+            ["S-A-Up", "\x1b[1;4A"],
+            ["S-A-Down", "\x1b[1;4B"],
+            ["C-A-S-Left", "\x1b[1;8D"],
+            ["C-A-S-Right", "\x1b[1;8C"],
+            ["C-A-S-Up", "\x1b[1;8A"],
+            ["C-A-S-Down", "\x1b[1;8B"],
+            ["A-a", "\x1ba"],
+            ["A-A", "\x1bA"],
+            ["A-z", "\x1bz"],
+            ["A-Z", "\x1bZ"],
+            ["A-1", "\x1b1"],
+            ["A-!", "\x1b!"],
+            ["F1", "\x1bOP"],
+            ["F2", "\x1bOQ"],
+            ["F3", "\x1bOR"],
+            ["F4", "\x1bOS"],
+            ["F5", "\x1b[15~"],
+            ["F6", "\x1b[17~"],
+            ["F7", "\x1b[18~"],
+            ["F8", "\x1b[19~"],
+            ["F9", "\x1b[20~"],
+            ["F10", "\x1b[21~"],
+            ["F11", "\x1b[23~"],
+            ["F12", "\x1b[24~"],
+            ["C-F1", "\x1b[1;5P"],
+            ["C-F2", "\x1b[1;5Q"],
+            ["C-F3", "\x1b[1;5R"],
+            ["C-F4", "\x1b[1;5S"],
+            ["C-F5", "\x1b[15;5~"],
+            ["C-F6", "\x1b[17;5~"],
+            ["C-F7", "\x1b[18;5~"],
+            ["C-F8", "\x1b[19;5~"],
+            ["C-F9", "\x1b[20;5~"],
+            ["C-F10", "\x1b[21;5~"],
+            ["C-F11", "\x1b[23;5~"],
+            ["C-F12", "\x1b[24;5~"],
+            ["S-F1", "\x1b[1;2P"],
+            ["S-F2", "\x1b[1;2Q"],
+            ["S-F3", "\x1b[1;2R"],
+            ["S-F4", "\x1b[1;2S"],
+            ["S-F5", "\x1b[15;2~"],
+            ["S-F6", "\x1b[17;2~"],
+            ["S-F7", "\x1b[18;2~"],
+            ["S-F8", "\x1b[19;2~"],
+            ["S-F9", "\x1b[20;2~"],
+            ["S-F10", "\x1b[21;2~"],
+            ["S-F11", "\x1b[23;2~"],
+            ["S-F12", "\x1b[24;2~"],
+            ["A-F1", "\x1b[1;3P"],
+            ["A-F2", "\x1b[1;3Q"],
+            ["A-F3", "\x1b[1;3R"],
+            ["A-F4", "\x1b[1;3S"],
+            ["A-F5", "\x1b[15;3~"],
+            ["A-F6", "\x1b[17;3~"],
+            ["A-F7", "\x1b[18;3~"],
+            ["A-F8", "\x1b[19;3~"],
+            ["A-F9", "\x1b[20;3~"],
+            ["A-F10", "\x1b[21;3~"],
+            ["A-F11", "\x1b[23;3~"],
+            ["A-F12", "\x1b[24;3~"],
+            ["C-Home", "\x1b[1;5H"],
+            ["S-Home", "\x1b[1;2H"],
+            ["A-Home", "\x1b[1;3H"],
+            ["C-End", "\x1b[1;5F"],
+            ["S-End", "\x1b[1;2F"],
+            ["A-End", "\x1b[1;3F"],
+            ["PageUp", "\x1b[5~"],
+            ["C-PageUp", "\x1b[5;5~"],
+            ["S-PageUp", "\x1b[5;2~"],
+            ["A-PageUp", "\x1b[5;3~"],
+            ["PageDown", "\x1b[6~"],
+            ["C-PageDown", "\x1b[6;5~"],
+            ["S-PageDown", "\x1b[6;2~"],
+            ["A-PageDown", "\x1b[6;3~"],
+            ["Insert", "\x1b[2~"],
+            ["C-Insert", "\x1b[2;5~"],
+            ["S-Insert", "\x1b[2;2~"],
+            ["A-Insert", "\x1b[2;3~"],
+            ["Delete", "\x1b[3~"],
+            ["C-Delete", "\x1b[3;5~"],
+            ["S-Delete", "\x1b[3;2~"],
+            ["A-Delete", "\x1b[3;3~"],
+            ["S-Tab", "\x1b[Z"],
+        ];
+
+        for [key, chars] in examples {
+            let command = parse_line(&format!(
+                "{{ \"type\": \"sendKeys\", \"keys\": [\"{key}\"] }}"
+            ))
+            .unwrap()
+            .command;
+
+            assert!(matches!(command, Command::Input(input) if input == vec![standard_key(chars)]));
+        }
+
+        let command = parse_line(
+            r#"{ "type": "sendKeys", "keys": ["hello", "Enter", "C-c", "A-^", "Left"] }"#,
+        )
+        .unwrap()
+        .command;
+
+        assert!(
+            matches!(command, Command::Input(input) if input == vec![standard_key("hello"), standard_key("\x0d"), standard_key("\x03"), standard_key("\x1b^"), cursor_key("\x1b[D", "\x1bOD")])
+        );
+    }
+
+    #[test]
+    fn parse_cursor_keys() {
+        let examples = [
+            ["Left", "\x1b[D", "\x1bOD"],
+            ["Right", "\x1b[C", "\x1bOC"],
+            ["Up", "\x1b[A", "\x1bOA"],
+            ["Down", "\x1b[B", "\x1bOB"],
+            ["Home", "\x1b[H", "\x1bOH"],
+            ["End", "\x1b[F", "\x1bOF"],
+        ];
+
+        for [key, seq1, seq2] in examples {
+            let command = parse_line(&format!(
+                "{{ \"type\": \"sendKeys\", \"keys\": [\"{key}\"] }}"
+            ))
+            .unwrap()
+            .command;
+
+            if let Command::Input(seqs) = command {
+                if let InputSeq::Cursor(seq3, seq4) = &seqs[0] {
+                    if seq1 == seq3 && seq2 == seq4 {
+                        continue;
+                    }
+
+                    panic!("expected {:?} {:?}, got {:?} {:?}", seq1, seq2, seq3, seq4);
+                }
+            }
+
+            panic!("expected {:?} {:?}", seq1, seq2);
+        }
+    }
+
+    #[test]
+    fn parse_send_keys_missing_args() {
+        parse_line(r#"{ "type": "sendKeys" }"#).expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_resize() {
+        let command = parse_line(r#"{ "type": "resize", "cols": 80, "rows": 24 }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Resize(80, 24)));
+    }
+
+    #[test]
+    fn parse_resize_missing_args() {
+        parse_line(r#"{ "type": "resize" }"#).expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_resize_invalid_size() {
+        parse_line(r#"{ "type": "resize", "cols": 0, "rows": 24 }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "resize", "cols": 80, "rows": 0 }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "resize", "cols": 65536, "rows": 24 }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "resize", "cols": 80, "rows": -1 }"#).expect_err("should fail");
+
+        let command = parse_line(r#"{ "type": "resize", "cols": 65535, "rows": 1 }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Resize(65535, 1)));
+    }
+
+    #[test]
+    fn parse_take_snapshot() {
+        let command = parse_line(r#"{ "type": "takeSnapshot" }"#).unwrap().command;
+        assert!(matches!(command, Command::Snapshot(Format::Text)));
+
+        let command = parse_line(r#"{ "type": "takeSnapshot", "format": "styled" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Snapshot(Format::Styled)));
+
+        let command = parse_line(r#"{ "type": "takeSnapshot", "format": "svg" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Snapshot(Format::Svg)));
+
+        let command = parse_line(r#"{ "type": "takeSnapshot", "format": "html" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Snapshot(Format::Html)));
+    }
+
+    #[test]
+    fn parse_take_snapshot_invalid_format() {
+        parse_line(r#"{ "type": "takeSnapshot", "format": "nope" }"#).expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_take_screenshot() {
+        let command = parse_line(r#"{ "type": "takeScreenshot" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Screenshot));
+    }
+
+    #[test]
+    fn parse_wait_for() {
+        let command = parse_line(r#"{ "type": "waitFor", "text": "$ ", "timeout": 1000 }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::WaitFor(_)));
+
+        let command = parse_line(
+            r#"{ "type": "waitFor", "regex": "\\d+", "region": { "col": 0, "row": 2, "cols": 80, "rows": 1 } }"#,
+        )
+        .unwrap()
+        .command;
+
+        assert!(matches!(command, Command::WaitFor(_)));
+    }
+
+    #[test]
+    fn parse_wait_for_invalid_args() {
+        parse_line(r#"{ "type": "waitFor" }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "waitFor", "text": "a", "regex": "b" }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "waitFor", "regex": "(" }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "waitFor", "text": "" }"#).expect_err("should fail");
+
+        parse_line(
+            r#"{ "type": "waitFor", "text": "a", "region": { "col": 0, "row": 0, "cols": 0, "rows": 1 } }"#,
+        )
+        .expect_err("should fail");
+
+        parse_line(
+            r#"{ "type": "waitFor", "regex": "a", "region": { "col": 0, "row": 0, "cols": 1, "rows": 0 } }"#,
+        )
+        .expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_wait_for_idle() {
+        let command = parse_line(r#"{ "type": "waitForIdle", "ms": 300, "timeout": 5000 }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::WaitFor(_)));
+    }
+
+    #[test]
+    fn parse_wait_for_idle_missing_args() {
+        parse_line(r#"{ "type": "waitForIdle" }"#).expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_get_scrollback() {
+        let command = parse_line(r#"{ "type": "getScrollback" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(
+            command,
+            Command::GetScrollback(0, None, Format::Text)
+        ));
+
+        let command = parse_line(
+            r#"{ "type": "getScrollback", "offset": 10, "limit": 5, "format": "styled" }"#,
+        )
+        .unwrap()
+        .command;
+        assert!(matches!(
+            command,
+            Command::GetScrollback(10, Some(5), Format::Styled)
+        ));
+    }
+
+    #[test]
+    fn parse_restart() {
+        let command = parse_line(r#"{ "type": "restart" }"#).unwrap().command;
+        assert!(matches!(command, Command::Restart));
+    }
+
+    #[test]
+    fn parse_control() {
+        let command = parse_line(r#"{ "type": "takeControl" }"#).unwrap().command;
+        assert!(matches!(command, Command::TakeControl));
+
+        let command = parse_line(r#"{ "type": "releaseControl" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::ReleaseControl));
+    }
+
+    #[test]
+    fn parse_seek() {
+        let command = parse_line(r#"{ "type": "seek", "time": 12.5 }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Seek(t) if t == 12.5));
+
+        parse_line(r#"{ "type": "seek" }"#).expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_paste() {
+        let command = parse_line(r#"{ "type": "paste", "payload": "a\nb" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(command, Command::Paste(text) if text == "a\nb"));
+
+        parse_line(r#"{ "type": "paste" }"#).expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_mouse() {
+        let command = parse_line(r#"{ "type": "mouse", "action": "click", "col": 3, "row": 4 }"#)
+            .unwrap()
+            .command;
+
+        let Command::Mouse(events) = command else {
+            panic!("expected mouse command");
+        };
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].action, MouseAction::Press(MouseButton::Left));
+        assert_eq!(events[1].action, MouseAction::Release(MouseButton::Left));
+        assert_eq!((events[0].col, events[0].row), (3, 4));
+
+        let command = parse_line(
+            r#"{ "type": "mouse", "action": "scroll", "direction": "up", "col": 0, "row": 0, "shift": true }"#,
+        )
+        .unwrap()
+        .command;
+
+        let Command::Mouse(events) = command else {
+            panic!("expected mouse command");
+        };
+
+        assert_eq!(events[0].action, MouseAction::Scroll(ScrollDirection::Up));
+        assert!(events[0].modifiers.shift);
+
+        let command = parse_line(r#"{ "type": "mouse", "action": "move", "col": 1, "row": 1 }"#)
+            .unwrap()
+            .command;
+
+        assert!(
+            matches!(command, Command::Mouse(events) if events[0].action == MouseAction::Move(None))
+        );
+    }
+
+    #[test]
+    fn parse_mouse_invalid_args() {
+        parse_line(r#"{ "type": "mouse", "action": "click" }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "mouse", "action": "scroll", "col": 0, "row": 0 }"#)
+            .expect_err("should fail");
+        parse_line(r#"{ "type": "mouse", "action": "tap", "col": 0, "row": 0 }"#)
+            .expect_err("should fail");
+        parse_line(r#"{ "type": "mouse", "action": "press", "button": "x", "col": 0, "row": 0 }"#)
+            .expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_signal() {
+        let command = parse_line(r#"{ "type": "signal", "name": "SIGINT" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(
+            command,
+            Command::Signal(Signal::SIGINT, SignalTarget::Group)
+        ));
+
+        let command = parse_line(r#"{ "type": "signal", "name": "kill", "target": "process" }"#)
+            .unwrap()
+            .command;
+        assert!(matches!(
+            command,
+            Command::Signal(Signal::SIGKILL, SignalTarget::Process)
+        ));
+    }
+
+    #[test]
+    fn parse_signal_invalid_args() {
+        parse_line(r#"{ "type": "signal" }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "signal", "name": "SIGNOPE" }"#).expect_err("should fail");
+        parse_line(r#"{ "type": "signal", "name": "INT", "target": "x" }"#)
+            .expect_err("should fail");
+    }
+
+    #[test]
+    fn parse_request_id() {
+        let request = parse_line(r#"{ "type": "takeSnapshot", "id": 42 }"#).unwrap();
+        assert_eq!(request.id, Some(json!(42)));
+
+        let request = parse_line(r#"{ "type": "takeSnapshot", "id": "abc" }"#).unwrap();
+        assert_eq!(request.id, Some(json!("abc")));
+
+        let request = parse_line(r#"{ "type": "takeSnapshot" }"#).unwrap();
+        assert_eq!(request.id, None);
+
+        let (id, _) = parse_line(r#"{ "type": "resize", "id": 7 }"#).unwrap_err();
+        assert_eq!(id, Some(json!(7)));
+
+        let (id, _) = parse_line(r#"{ "type": "nope", "id": 8 }"#).unwrap_err();
+        assert_eq!(id, Some(json!(8)));
+    }
+
+    #[test]
+    fn parse_invalid_json() {
+        parse_line("{").expect_err("should fail");
+    }
+}
@@ -0,0 +1,148 @@
+use super::{parser, Subscription};
+use crate::command::{ClientId, Id, Reply, Request};
+use crate::session::{self, Event};
+use anyhow::Result;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, Deserialize)]
+struct SubscribeArgs {
+    events: String,
+}
+
+/// Serves clients connecting to the Unix socket. Each client speaks the same
+/// newline-delimited JSON protocol as STDIO API, with its own subscription.
+pub async fn start(
+    listener: UnixListener,
+    command_tx: mpsc::Sender<Request>,
+    clients_tx: mpsc::Sender<session::Client>,
+    sub: Subscription,
+) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let command_tx = command_tx.clone();
+        let clients_tx = clients_tx.clone();
+
+        tokio::spawn(async move {
+            let client = ClientId::next();
+            let _ = handle_client(stream, command_tx.clone(), clients_tx, sub, client).await;
+
+            // the control taken by the connection ends with it
+            let _ = command_tx.send(Request::release_control(client)).await;
+        });
+    }
+}
+
+async fn handle_client(
+    stream: UnixStream,
+    command_tx: mpsc::Sender<Request>,
+    clients_tx: mpsc::Sender<session::Client>,
+    mut sub: Subscription,
+    client: ClientId,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut events = session::stream(&clients_tx, Some(client)).await?;
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                match line? {
+                    Some(line) => {
+                        match parse_subscribe(&line) {
+                            Some(Ok(new_sub)) => {
+                                // starts over with init event reflecting the current state
+                                sub = new_sub;
+                                events = session::stream(&clients_tx, Some(client)).await?;
+                            }
+
+                            Some(Err((id, e))) => {
+                                if sub.reports_parse_error(&id) {
+                                    write(&mut writer, Event::Error(Reply { client, id }, e)).await?;
+                                }
+                            }
+
+                            None => {
+                                match parser::parse_line(&line) {
+                                    Ok(request) => command_tx.send(Request { client, ..request }).await?,
+
+                                    Err((id, e)) => {
+                                        if sub.reports_parse_error(&id) {
+                                            write(&mut writer, Event::Error(Reply { client, id }, e)).await?;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    None => break
+                }
+            }
+
+            event = events.next() => {
+                match event {
+                    Some(Ok(e)) if sub.includes(&e) => write(&mut writer, e).await?,
+                    Some(_) => (),
+                    None => break
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses `subscribe` command, which is handled by the connection itself
+/// rather than the session. Returns `None` for other commands.
+fn parse_subscribe(line: &str) -> Option<Result<Subscription, (Option<Id>, String)>> {
+    let value = serde_json::from_str::<serde_json::Value>(line).ok()?;
+
+    if value["type"] != "subscribe" {
+        return None;
+    }
+
+    let id = value.get("id").cloned();
+
+    let sub = serde_json::from_value::<SubscribeArgs>(value)
+        .map_err(|e| e.to_string())
+        .and_then(|args| args.events.parse());
+
+    Some(sub.map_err(|e| (id, e)))
+}
+
+async fn write(writer: &mut (impl AsyncWriteExt + Unpin), event: Event) -> Result<()> {
+    writer
+        .write_all(format!("{}\n", event.to_json()).as_bytes())
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_subscribe;
+    use serde_json::json;
+
+    #[test]
+    fn subscribe() {
+        let sub = parse_subscribe(r#"{ "type": "subscribe", "events": "init,output" }"#)
+            .unwrap()
+            .unwrap();
+        assert!(sub.init && sub.output && !sub.snapshot);
+
+        let (id, _) = parse_subscribe(r#"{ "type": "subscribe", "events": "nope", "id": 1 }"#)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(id, Some(json!(1)));
+
+        assert!(parse_subscribe(r#"{ "type": "subscribe" }"#)
+            .unwrap()
+            .is_err());
+        assert!(parse_subscribe(r#"{ "type": "input", "payload": "x" }"#).is_none());
+        assert!(parse_subscribe("nope").is_none());
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::ServerConfig;
+
+/// Names the self-signed certificate is valid for.
+const SELF_SIGNED_NAMES: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// Builds the TLS config from a PEM certificate chain and private key.
+pub fn load(cert_path: &Path, key_path: &Path) -> Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .with_context(|| format!("cannot open certificate {}", cert_path.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("cannot read certificate {}", cert_path.display()))?;
+
+    if certs.is_empty() {
+        bail!("no certificate found in {}", cert_path.display());
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("cannot read private key {}", key_path.display()))?;
+
+    build(certs, key)
+}
+
+/// Builds the TLS config with a freshly generated self-signed certificate for
+/// local use, plus the additional name (e.g. the listen address) if given.
+pub fn self_signed(name: Option<String>) -> Result<Arc<ServerConfig>> {
+    let mut names: Vec<String> = SELF_SIGNED_NAMES.iter().map(|n| n.to_string()).collect();
+    names.extend(name.filter(|n| !names.contains(n)));
+
+    let certified = rcgen::generate_simple_self_signed(names)
+        .context("cannot generate self-signed certificate")?;
+
+    let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+
+    build(vec![certified.cert.der().clone()], key.into())
+}
+
+fn build(
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<Arc<ServerConfig>> {
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("invalid certificate or private key")?;
+
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load, self_signed};
+
+    #[test]
+    fn load_pem_files() {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let dir = std::env::temp_dir().join(format!("ht-tls-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        let config = load(&cert_path, &key_path).unwrap();
+        assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+
+        assert!(load(&key_path, &key_path).is_err());
+        assert!(load(&cert_path, &cert_path).is_err());
+        assert!(load(&dir.join("nope.pem"), &key_path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn self_signed_config() {
+        assert!(self_signed(None).is_ok());
+        assert!(self_signed(Some("10.0.0.1".to_owned())).is_ok());
+    }
+}
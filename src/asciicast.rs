@@ -0,0 +1,237 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+/// asciicast v2 file header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub cols: usize,
+    pub rows: usize,
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Output(f64, String),
+    Input(f64, String),
+    Resize(f64, usize, usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub header: Header,
+    pub events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct RawHeader {
+    version: u8,
+    width: usize,
+    height: usize,
+    timestamp: Option<u64>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+
+        Self::parse(&content).with_context(|| format!("invalid asciicast file {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+
+        let Some((_, line)) = lines.next() else {
+            bail!("missing header");
+        };
+
+        let header: RawHeader = serde_json::from_str(line).context("invalid header")?;
+
+        if header.version != 2 {
+            bail!("unsupported asciicast version: {}", header.version);
+        }
+
+        if !is_valid_size(header.width, header.height) {
+            bail!("invalid size {}x{}", header.width, header.height);
+        }
+
+        let mut events = Vec::new();
+
+        for (i, line) in lines {
+            let (time, code, data): (f64, String, String) = serde_json::from_str(line)
+                .with_context(|| format!("invalid event on line {}", i + 1))?;
+
+            if Duration::try_from_secs_f64(time).is_err() {
+                bail!("invalid time of event on line {}", i + 1);
+            }
+
+            let event = match code.as_str() {
+                "o" => Event::Output(time, data),
+                "i" => Event::Input(time, data),
+
+                "r" => match data.split_once('x').map(|(c, r)| (c.parse(), r.parse())) {
+                    Some((Ok(cols), Ok(rows))) if is_valid_size(cols, rows) => {
+                        Event::Resize(time, cols, rows)
+                    }
+
+                    _ => bail!("invalid resize event on line {}", i + 1),
+                },
+
+                // markers and other event types don't affect the terminal
+                _ => continue,
+            };
+
+            events.push(event);
+        }
+
+        Ok(Recording {
+            header: Header {
+                cols: header.width,
+                rows: header.height,
+                timestamp: header.timestamp,
+            },
+            events,
+        })
+    }
+}
+
+impl Header {
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut header = json!({
+            "version": 2,
+            "width": self.cols,
+            "height": self.rows,
+            "env": {
+                "TERM": "xterm-256color",
+            },
+        });
+
+        if let Some(timestamp) = self.timestamp {
+            header["timestamp"] = json!(timestamp);
+        }
+
+        header
+    }
+}
+
+impl Event {
+    pub fn time(&self) -> f64 {
+        match self {
+            Event::Output(time, _) | Event::Input(time, _) | Event::Resize(time, _, _) => *time,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Event::Output(time, data) => json!([round(*time), "o", data]),
+            Event::Input(time, data) => json!([round(*time), "i", data]),
+            Event::Resize(time, cols, rows) => json!([round(*time), "r", format!("{cols}x{rows}")]),
+        }
+    }
+}
+
+/// Tells whether the terminal can have the size, which must fit the PTY's.
+fn is_valid_size(cols: usize, rows: usize) -> bool {
+    let range = 1..=u16::MAX as usize;
+
+    range.contains(&cols) && range.contains(&rows)
+}
+
+/// Rounds time to microseconds, which is more than enough for playback and
+/// keeps the file compact.
+fn round(time: f64) -> f64 {
+    (time * 1_000_000.0).round() / 1_000_000.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Event, Header, Recording};
+
+    #[test]
+    fn header() {
+        let header = Header {
+            cols: 80,
+            rows: 24,
+            timestamp: Some(1700000000),
+        };
+
+        assert_eq!(
+            header.to_json().to_string(),
+            r#"{"env":{"TERM":"xterm-256color"},"height":24,"timestamp":1700000000,"version":2,"width":80}"#
+        );
+    }
+
+    #[test]
+    fn events() {
+        assert_eq!(
+            Event::Output(1.0000004, "ls\r\n".to_owned())
+                .to_json()
+                .to_string(),
+            r#"[1.0,"o","ls\r\n"]"#
+        );
+
+        assert_eq!(
+            Event::Input(0.5, "\u{3}".to_owned()).to_json().to_string(),
+            r#"[0.5,"i","\u0003"]"#
+        );
+
+        assert_eq!(
+            Event::Resize(2.25, 100, 30).to_json().to_string(),
+            r#"[2.25,"r","100x30"]"#
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let recording = Recording::parse(concat!(
+            r#"{"version":2,"width":80,"height":24}"#,
+            "\n",
+            r#"[0.5,"o","hello"]"#,
+            "\n",
+            r#"[1.0,"m","marker"]"#,
+            "\n",
+            r#"[1.5,"r","100x30"]"#,
+            "\n\n"
+        ))
+        .unwrap();
+
+        assert_eq!((recording.header.cols, recording.header.rows), (80, 24));
+
+        assert_eq!(
+            recording.events,
+            vec![
+                Event::Output(0.5, "hello".to_owned()),
+                Event::Resize(1.5, 100, 30)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Recording::parse("").is_err());
+        assert!(Recording::parse(r#"{"version":1,"width":80,"height":24}"#).is_err());
+        assert!(
+            Recording::parse("{\"version\":2,\"width\":80,\"height\":24}\n[1.0,\"r\",\"x\"]")
+                .is_err()
+        );
+        assert!(Recording::parse("{\"version\":2,\"width\":80,\"height\":24}\n[1.0]").is_err());
+        assert!(Recording::parse(r#"{"version":2,"width":0,"height":24}"#).is_err());
+        assert!(Recording::parse(r#"{"version":2,"width":80,"height":65536}"#).is_err());
+
+        for event in [
+            r#"[1.0,"r","0x24"]"#,
+            r#"[1.0,"r","80x70000"]"#,
+            r#"[-1.0,"o","a"]"#,
+            r#"[1e300,"o","a"]"#,
+        ] {
+            let content = format!("{{\"version\":2,\"width\":80,\"height\":24}}\n{event}");
+            assert!(Recording::parse(&content).is_err(), "{event}");
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use crate::api::Subscription;
+use crate::render::theme::Theme;
+use crate::replay::Speed;
 use anyhow::bail;
 use clap::Parser;
 use nix::pty;
-use std::{fmt::Display, net::SocketAddr, ops::Deref, str::FromStr};
+use std::{fmt::Display, net::SocketAddr, ops::Deref, path::PathBuf, str::FromStr};
 
 #[derive(Debug, Parser)]
 #[clap(version, about)]
@@ -20,9 +22,81 @@ pub struct Cli {
     #[arg(short, long, value_name = "LISTEN_ADDR", default_missing_value = "127.0.0.1:0", num_args = 0..=1)]
     pub listen: Option<SocketAddr>,
 
+    /// Let the user type into the terminal from the live preview page
+    #[arg(long, requires = "listen")]
+    pub interactive_preview: bool,
+
+    /// Serve HTTPS with the certificate chain from a PEM file
+    #[arg(long, value_name = "PATH", requires_all = ["listen", "tls_key"])]
+    pub tls_cert: Option<PathBuf>,
+
+    /// Private key for the certificate, in a PEM file
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Serve HTTPS with a generated self-signed certificate
+    #[arg(long, requires = "listen", conflicts_with = "tls_cert")]
+    pub tls_self_signed: bool,
+
+    /// Require a token for the HTTP server, generated if not given
+    #[arg(long, value_name = "TOKEN", num_args = 0..=1, default_missing_value = "", requires = "listen")]
+    pub auth_token: Option<String>,
+
+    /// Token granting read-only access to the HTTP server, generated if not given
+    #[arg(long, value_name = "TOKEN", num_args = 0..=1, default_missing_value = "", requires = "auth_token")]
+    pub read_only_token: Option<String>,
+
+    /// Serve the STDIO API protocol on a Unix socket
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Don't read commands from stdin, and keep running when it's closed
+    #[arg(long)]
+    pub no_stdin: bool,
+
     /// Subscribe to events
     #[arg(long, value_name = "EVENTS")]
     pub subscribe: Option<Subscription>,
+
+    /// Time without output after which the terminal is considered idle
+    #[arg(long, value_name = "MILLISECONDS", default_value_t = 200)]
+    pub idle_time: u64,
+
+    /// Number of lines scrolled off the top of the screen to retain
+    #[arg(long, value_name = "LINES", default_value_t = 1000)]
+    pub scrollback: usize,
+
+    /// Keep running after the process exits, until stdin is closed
+    #[arg(long)]
+    pub keep_alive: bool,
+
+    /// Record the session to a file in asciicast v2 format
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Include input in the recording
+    #[arg(long, requires = "record")]
+    pub record_input: bool,
+
+    /// Replay an asciicast recording instead of running a command
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Replay speed, a multiplier or "instant"
+    #[arg(long, value_name = "SPEED", default_value = "1", requires = "replay")]
+    pub speed: Speed,
+
+    /// Color theme for rendered images, a built-in theme name or a list of colors
+    #[arg(long, value_name = "THEME", default_value = "asciinema")]
+    pub theme: Theme,
+
+    /// Font for characters missing from the built-in one in PNG images, e.g. CJK
+    #[arg(long, value_name = "PATH")]
+    pub fallback_font: Vec<PathBuf>,
+
+    /// Save an SVG image of the terminal's final state to a file
+    #[arg(long, value_name = "PATH")]
+    pub snapshot_svg: Option<PathBuf>,
 }
 
 impl Cli {
@@ -35,6 +109,15 @@ impl Cli {
 pub struct Size(pty::Winsize);
 
 impl Size {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Size(pty::Winsize {
+            ws_col: cols,
+            ws_row: rows,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        })
+    }
+
     pub fn cols(&self) -> usize {
         self.0.ws_col as usize
     }
@@ -53,14 +136,7 @@ impl FromStr for Size {
                 let cols: u16 = cols.parse()?;
                 let rows: u16 = rows.parse()?;
 
-                let winsize = pty::Winsize {
-                    ws_col: cols,
-                    ws_row: rows,
-                    ws_xpixel: 0,
-                    ws_ypixel: 0,
-                };
-
-                Ok(Size(winsize))
+                Ok(Size::new(cols, rows))
             }
 
             None => {
@@ -1,8 +1,70 @@
+use crate::pty::SignalTarget;
+use crate::render;
+use crate::tracker::{Modes, MouseEncoding, MouseTracking};
+use crate::wait::Wait;
+use nix::sys::signal::Signal;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Client-chosen request identifier, echoed back on the resulting event.
+pub type Id = serde_json::Value;
+
+#[derive(Debug)]
+pub struct Request {
+    pub id: Option<Id>,
+    pub command: Command,
+    pub client: ClientId,
+}
+
+impl Request {
+    /// Releases the control of the terminal on behalf of a client which has
+    /// gone away.
+    pub fn release_control(client: ClientId) -> Self {
+        Request {
+            id: None,
+            command: Command::ReleaseControl,
+            client,
+        }
+    }
+}
+
+/// Where the result of a request goes: the client which sent it, along with
+/// the id it chose, if any.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub client: ClientId,
+    pub id: Option<Id>,
+}
+
+/// Identifies the sender of a request, e.g. a socket connection, to tell the
+/// client which took control of the terminal from others. The default one is
+/// STDIO API.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct ClientId(u64);
+
+impl ClientId {
+    pub fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+
+        ClientId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 #[derive(Debug)]
 pub enum Command {
     Input(Vec<InputSeq>),
-    Snapshot,
+    Snapshot(render::Format),
+    Screenshot,
     Resize(usize, usize),
+    WaitFor(Wait),
+    GetScrollback(usize, Option<usize>, render::Format),
+    Restart,
+    Signal(Signal, SignalTarget),
+    Mouse(Vec<Mouse>),
+    Paste(String),
+    Seek(f64),
+    TakeControl,
+    ReleaseControl,
 }
 
 #[derive(Debug, PartialEq)]
@@ -11,6 +73,46 @@ pub enum InputSeq {
     Cursor(String, String),
 }
 
+/// Mouse event at a 0-based cell position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mouse {
+    pub action: MouseAction,
+    pub col: usize,
+    pub row: usize,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseAction {
+    Press(MouseButton),
+    Release(MouseButton),
+    /// Motion, with the button held down, if any
+    Move(Option<MouseButton>),
+    Scroll(ScrollDirection),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollDirection {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
 pub fn seqs_to_bytes(seqs: &[InputSeq], app_mode: bool) -> Vec<u8> {
     let mut bytes = Vec::new();
 
@@ -28,3 +130,237 @@ fn seq_as_bytes(seq: &InputSeq, app_mode: bool) -> &[u8] {
         (InputSeq::Cursor(_seq1, seq2), true) => seq2.as_bytes(),
     }
 }
+
+/// Encodes pasted text, wrapping it in bracketed paste markers when the
+/// application enabled bracketed paste mode. End markers embedded in the text
+/// are removed so it can't break out of the paste.
+pub fn paste_to_bytes(text: &str, bracketed: bool) -> Vec<u8> {
+    if !bracketed {
+        return text.as_bytes().to_vec();
+    }
+
+    let mut text = text.to_owned();
+
+    while text.contains(PASTE_END) {
+        text = text.replace(PASTE_END, "");
+    }
+
+    format!("{PASTE_START}{text}{PASTE_END}").into_bytes()
+}
+
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+/// Encodes mouse events the way a terminal reports them to the application,
+/// according to its mouse tracking mode and encoding. Events which aren't
+/// reported in the current tracking mode are skipped.
+pub fn mouse_to_bytes(events: &[Mouse], modes: &Modes) -> Vec<u8> {
+    events
+        .iter()
+        .filter_map(|event| encode_mouse(event, modes.mouse_tracking, modes.mouse_encoding))
+        .flatten()
+        .collect()
+}
+
+fn encode_mouse(
+    event: &Mouse,
+    tracking: MouseTracking,
+    encoding: MouseEncoding,
+) -> Option<Vec<u8>> {
+    use MouseAction::*;
+
+    let reported = match (event.action, tracking) {
+        (_, MouseTracking::None) => false,
+        (Press(_) | Scroll(_), _) => true,
+        (Release(_), MouseTracking::X10) => false,
+        (Release(_), _) => true,
+        (Move(Some(_)), MouseTracking::Button | MouseTracking::Any) => true,
+        (Move(None), MouseTracking::Any) => true,
+        (Move(_), _) => false,
+    };
+
+    if !reported {
+        return None;
+    }
+
+    let release = matches!(event.action, Release(_));
+
+    let mut code = match event.action {
+        Press(button) => button_code(button),
+        Release(button) if encoding == MouseEncoding::Sgr => button_code(button),
+        Release(_) => 3,
+        Move(Some(button)) => button_code(button) + 32,
+        Move(None) => 3 + 32,
+        Scroll(ScrollDirection::Up) => 64,
+        Scroll(ScrollDirection::Down) => 65,
+    };
+
+    if tracking != MouseTracking::X10 {
+        let m = event.modifiers;
+        code += (m.shift as u32) * 4 + (m.alt as u32) * 8 + (m.ctrl as u32) * 16;
+    }
+
+    let x = u32::try_from(event.col).ok()? + 1;
+    let y = u32::try_from(event.row).ok()? + 1;
+
+    match encoding {
+        MouseEncoding::Sgr => {
+            let suffix = if release { 'm' } else { 'M' };
+            Some(format!("\x1b[<{code};{x};{y}{suffix}").into_bytes())
+        }
+
+        MouseEncoding::Urxvt => Some(format!("\x1b[{};{x};{y}M", code + 32).into_bytes()),
+
+        // values up to 2015 are encoded as (at most 2-byte) UTF-8 characters
+        MouseEncoding::Utf8 => {
+            let mut seq = String::from("\x1b[M");
+
+            for value in [code, x, y] {
+                if value + 32 >= 2048 {
+                    return None;
+                }
+
+                seq.push(char::from_u32(value + 32)?);
+            }
+
+            Some(seq.into_bytes())
+        }
+
+        MouseEncoding::Default => {
+            let mut seq = b"\x1b[M".to_vec();
+
+            for value in [code, x, y] {
+                seq.push(u8::try_from(value + 32).ok()?);
+            }
+
+            Some(seq)
+        }
+    }
+}
+
+fn button_code(button: MouseButton) -> u32 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        mouse_to_bytes, paste_to_bytes, Modifiers, Mouse, MouseAction, MouseButton, ScrollDirection,
+    };
+    use crate::tracker::{Modes, MouseEncoding, MouseTracking};
+
+    fn modes(tracking: MouseTracking, encoding: MouseEncoding) -> Modes {
+        Modes {
+            mouse_tracking: tracking,
+            mouse_encoding: encoding,
+            ..Default::default()
+        }
+    }
+
+    fn mouse(action: MouseAction, col: usize, row: usize) -> Mouse {
+        Mouse {
+            action,
+            col,
+            row,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    #[test]
+    fn mouse_default_encoding() {
+        let modes = modes(MouseTracking::Normal, MouseEncoding::Default);
+        let press = mouse(MouseAction::Press(MouseButton::Left), 0, 0);
+        let release = mouse(MouseAction::Release(MouseButton::Left), 9, 4);
+
+        assert_eq!(mouse_to_bytes(&[press], &modes), b"\x1b[M !!");
+        assert_eq!(mouse_to_bytes(&[release], &modes), b"\x1b[M#*%");
+
+        let far = mouse(MouseAction::Press(MouseButton::Left), 300, 0);
+
+        assert!(mouse_to_bytes(&[far], &modes).is_empty());
+    }
+
+    #[test]
+    fn mouse_sgr_encoding() {
+        let modes = modes(MouseTracking::Button, MouseEncoding::Sgr);
+
+        let events = [
+            mouse(MouseAction::Press(MouseButton::Right), 300, 1),
+            mouse(MouseAction::Move(Some(MouseButton::Right)), 301, 1),
+            mouse(MouseAction::Release(MouseButton::Right), 301, 1),
+        ];
+
+        assert_eq!(
+            mouse_to_bytes(&events, &modes),
+            b"\x1b[<2;301;2M\x1b[<34;302;2M\x1b[<2;302;2m"
+        );
+
+        let scroll = Mouse {
+            modifiers: Modifiers {
+                ctrl: true,
+                ..Default::default()
+            },
+            ..mouse(MouseAction::Scroll(ScrollDirection::Down), 0, 0)
+        };
+
+        assert_eq!(mouse_to_bytes(&[scroll], &modes), b"\x1b[<81;1;1M");
+    }
+
+    #[test]
+    fn mouse_utf8_and_urxvt_encoding() {
+        let press = mouse(MouseAction::Press(MouseButton::Left), 199, 0);
+
+        assert_eq!(
+            mouse_to_bytes(&[press], &modes(MouseTracking::Normal, MouseEncoding::Utf8)),
+            "\x1b[M \u{e8}!".as_bytes()
+        );
+
+        assert_eq!(
+            mouse_to_bytes(
+                &[press],
+                &modes(MouseTracking::Normal, MouseEncoding::Urxvt)
+            ),
+            b"\x1b[32;200;1M"
+        );
+    }
+
+    #[test]
+    fn mouse_tracking_modes() {
+        let events = [
+            mouse(MouseAction::Press(MouseButton::Left), 0, 0),
+            mouse(MouseAction::Move(Some(MouseButton::Left)), 1, 0),
+            mouse(MouseAction::Move(None), 2, 0),
+            mouse(MouseAction::Release(MouseButton::Left), 2, 0),
+        ];
+
+        let count = |tracking| {
+            mouse_to_bytes(&events, &modes(tracking, MouseEncoding::Sgr))
+                .iter()
+                .filter(|&&b| b == 0x1b)
+                .count()
+        };
+
+        assert_eq!(count(MouseTracking::None), 0);
+        assert_eq!(count(MouseTracking::X10), 1);
+        assert_eq!(count(MouseTracking::Normal), 2);
+        assert_eq!(count(MouseTracking::Button), 3);
+        assert_eq!(count(MouseTracking::Any), 4);
+    }
+
+    #[test]
+    fn paste() {
+        assert_eq!(paste_to_bytes("ls\npwd\n", false), b"ls\npwd\n");
+        assert_eq!(
+            paste_to_bytes("ls\npwd\n", true),
+            b"\x1b[200~ls\npwd\n\x1b[201~"
+        );
+        assert_eq!(
+            paste_to_bytes("a\x1b[201~b\x1b[20\x1b[201~1~c", true),
+            b"\x1b[200~abc\x1b[201~"
+        );
+    }
+}
@@ -1,122 +1,643 @@
 mod api;
+mod asciicast;
 mod cli;
 mod command;
 mod locale;
 mod nbio;
 mod pty;
+mod recorder;
+mod render;
+mod replay;
 mod session;
-use anyhow::{Context, Result};
-use command::Command;
+mod tracker;
+mod wait;
+use anyhow::{bail, Context, Result};
+use command::{Command, Reply, Request};
+use nix::sys::stat;
+use render::theme::Theme;
 use session::Session;
+use std::collections::VecDeque;
+use std::future;
 use std::net::{SocketAddr, TcpListener};
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::UnixListener;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_rustls::rustls::ServerConfig;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     locale::check_utf8_locale()?;
     let cli = cli::Cli::new();
 
-    let (input_tx, input_rx) = mpsc::channel(1024);
-    let (output_tx, output_rx) = mpsc::channel(1024);
+    if !cli.fallback_font.is_empty() {
+        render::font::set_fallbacks(&cli.fallback_font)?;
+    }
+
     let (command_tx, command_rx) = mpsc::channel(1024);
     let (clients_tx, clients_rx) = mpsc::channel(1);
 
-    start_http_api(cli.listen, clients_tx.clone()).await?;
-    let api = start_stdio_api(command_tx, clients_tx, cli.subscribe.unwrap_or_default());
-    let (pid, pty) = start_pty(cli.command, &cli.size, input_rx, output_tx)?;
-    let session = build_session(&cli.size, pid);
-    run_event_loop(output_rx, input_tx, command_rx, clients_rx, session, api).await?;
-    pty.await?
+    let auth = build_auth(cli.auth_token, cli.read_only_token, cli.listen)?;
+
+    let tls = load_tls(
+        cli.tls_cert.as_deref(),
+        cli.tls_key.as_deref(),
+        cli.tls_self_signed,
+        cli.listen,
+    )?;
+
+    start_http_api(
+        cli.listen,
+        command_tx.clone(),
+        clients_tx.clone(),
+        auth,
+        tls,
+        cli.interactive_preview,
+    )
+    .await?;
+    let recording = create_recording(cli.record.as_deref())?;
+    let sub = cli.subscribe.unwrap_or_default();
+
+    start_socket_api(
+        cli.socket.as_deref(),
+        command_tx.clone(),
+        clients_tx.clone(),
+        sub,
+    )?;
+
+    let mut api = start_stdio_api(command_tx, clients_tx, sub, !cli.no_stdin);
+
+    let (source, size) = match cli.replay {
+        Some(path) => {
+            let recording = asciicast::Recording::load(&path)?;
+            let header = &recording.header;
+            let size = cli::Size::new(header.cols as u16, header.rows as u16);
+
+            (Source::Replay(Arc::new(recording), cli.speed), size)
+        }
+
+        None => (Source::Command(cli.command.join(" ")), cli.size),
+    };
+
+    let (pid, process) = start_process(&source, &size, cli.keep_alive)?;
+    let mut session = build_session(&size, pid, cli.idle_time, cli.scrollback, cli.theme);
+    let recorder = start_recorder(recording, cli.record_input, &mut session);
+    let result = run_event_loop(
+        source,
+        cli.keep_alive,
+        command_rx,
+        clients_rx,
+        session,
+        process,
+        &mut api,
+    )
+    .await;
+
+    if let Some(path) = &cli.socket {
+        let _ = std::fs::remove_file(path);
+    }
+
+    let (session, status) = result?;
+
+    if let Some(path) = cli.snapshot_svg {
+        std::fs::write(&path, session.svg())
+            .with_context(|| format!("cannot write snapshot to {}", path.display()))?;
+    }
+
+    // dropping the session ends the event stream, letting the STDIO API print
+    // the final events
+    drop(session);
+
+    if status.is_some() {
+        let _ = api.await;
+    }
+
+    if let Some(recorder) = recorder {
+        recorder.await??;
+    }
+
+    if let Some(status) = status {
+        process::exit(status.code());
+    }
+
+    Ok(())
+}
+
+/// What runs in the terminal.
+enum Source {
+    Command(String),
+    Replay(Arc<asciicast::Recording>, replay::Speed),
+}
+
+struct Process {
+    input_tx: mpsc::Sender<pty::Input>,
+    output_rx: mpsc::Receiver<pty::Output>,
+    handle: JoinHandle<Result<pty::ExitStatus>>,
+    pending_resizes: VecDeque<Reply>,
+    pending_signals: VecDeque<Reply>,
+    seek_tx: Option<mpsc::Sender<f64>>,
+}
+
+impl Process {
+    /// Whether a recording is replayed, which doesn't take input.
+    fn is_replay(&self) -> bool {
+        self.seek_tx.is_some()
+    }
+
+    async fn stop(self) -> Result<pty::ExitStatus> {
+        let Process {
+            input_tx,
+            output_rx,
+            handle,
+            ..
+        } = self;
+
+        // the output isn't read anymore, so it must not block the process
+        // from finishing
+        drop(input_tx);
+        drop(output_rx);
+
+        handle.await?
+    }
 }
 
-fn build_session(size: &cli::Size, pid: i32) -> Session {
-    Session::new(size.cols(), size.rows(), pid)
+fn build_session(
+    size: &cli::Size,
+    pid: i32,
+    idle_time: u64,
+    scrollback: usize,
+    theme: Theme,
+) -> Session {
+    Session::new(
+        size.cols(),
+        size.rows(),
+        pid,
+        Duration::from_millis(idle_time),
+        scrollback,
+        theme,
+    )
 }
 
 fn start_stdio_api(
-    command_tx: mpsc::Sender<Command>,
+    command_tx: mpsc::Sender<Request>,
     clients_tx: mpsc::Sender<session::Client>,
     sub: api::Subscription,
+    read_input: bool,
 ) -> JoinHandle<Result<()>> {
-    tokio::spawn(api::stdio::start(command_tx, clients_tx, sub))
+    tokio::spawn(api::stdio::start(command_tx, clients_tx, sub, read_input))
 }
 
-fn start_pty(
-    command: Vec<String>,
-    size: &cli::Size,
-    input_rx: mpsc::Receiver<Vec<u8>>,
-    output_tx: mpsc::Sender<Vec<u8>>,
-) -> Result<(i32, JoinHandle<Result<()>>)> {
-    let command = command.join(" ");
+/// Creates the recording file upfront, so that ht fails early when it can't.
+fn create_recording(path: Option<&Path>) -> Result<Option<tokio::fs::File>> {
+    match path {
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("cannot create recording file {}", path.display()))?;
+
+            Ok(Some(tokio::fs::File::from_std(file)))
+        }
+
+        None => Ok(None),
+    }
+}
+
+fn start_recorder(
+    file: Option<tokio::fs::File>,
+    record_input: bool,
+    session: &mut Session,
+) -> Option<JoinHandle<Result<()>>> {
+    file.map(|file| tokio::spawn(recorder::start(file, record_input, session.sink())))
+}
+
+fn start_process(source: &Source, size: &cli::Size, keep_alive: bool) -> Result<(i32, Process)> {
+    match source {
+        Source::Command(command) => start_pty(command, size),
+        Source::Replay(recording, speed) => Ok((0, start_replay(recording, *speed, keep_alive))),
+    }
+}
+
+fn start_pty(command: &str, size: &cli::Size) -> Result<(i32, Process)> {
+    let (input_tx, input_rx) = mpsc::channel(1024);
+    let (output_tx, output_rx) = mpsc::channel(1024);
     eprintln!("launching \"{}\" in terminal of size {}", command, size);
-    let (pid, fut) = pty::spawn(command, size, input_rx, output_tx)?;
+    let (pid, fut) = pty::spawn(command.to_owned(), size, input_rx, output_tx)?;
+
+    let process = Process {
+        input_tx,
+        output_rx,
+        handle: tokio::spawn(fut),
+        pending_resizes: VecDeque::new(),
+        pending_signals: VecDeque::new(),
+        seek_tx: None,
+    };
 
-    Ok((pid, tokio::spawn(fut)))
+    Ok((pid, process))
+}
+
+fn start_replay(
+    recording: &Arc<asciicast::Recording>,
+    speed: replay::Speed,
+    keep_alive: bool,
+) -> Process {
+    let (input_tx, input_rx) = mpsc::channel(1024);
+    let (output_tx, output_rx) = mpsc::channel(1024);
+    let (seek_tx, seek_rx) = mpsc::channel(1);
+    eprintln!("replaying recording at {speed} speed");
+    let fut = replay::drive(
+        recording.clone(),
+        speed,
+        keep_alive,
+        input_rx,
+        output_tx,
+        seek_rx,
+    );
+
+    Process {
+        input_tx,
+        output_rx,
+        handle: tokio::spawn(fut),
+        pending_resizes: VecDeque::new(),
+        pending_signals: VecDeque::new(),
+        seek_tx: Some(seek_tx),
+    }
 }
 
 async fn start_http_api(
     listen_addr: Option<SocketAddr>,
+    command_tx: mpsc::Sender<Request>,
     clients_tx: mpsc::Sender<session::Client>,
+    auth: api::auth::Auth,
+    tls: Option<Arc<ServerConfig>>,
+    interactive_preview: bool,
 ) -> Result<()> {
     if let Some(addr) = listen_addr {
         let listener = TcpListener::bind(addr).context("cannot start HTTP listener")?;
-        tokio::spawn(api::http::start(listener, clients_tx).await?);
+
+        let server = api::http::start(
+            listener,
+            command_tx,
+            clients_tx,
+            auth,
+            tls,
+            interactive_preview,
+        );
+
+        tokio::spawn(server.await?);
+    }
+
+    Ok(())
+}
+
+fn load_tls(
+    cert: Option<&Path>,
+    key: Option<&Path>,
+    self_signed: bool,
+    listen_addr: Option<SocketAddr>,
+) -> Result<Option<Arc<ServerConfig>>> {
+    match (cert, key) {
+        (Some(cert), Some(key)) => Ok(Some(api::tls::load(cert, key)?)),
+
+        _ if self_signed => {
+            let ip = listen_addr
+                .map(|addr| addr.ip())
+                .filter(|ip| !ip.is_unspecified())
+                .map(|ip| ip.to_string());
+
+            eprintln!("using a self-signed certificate, browsers will show a warning");
+
+            Ok(Some(api::tls::self_signed(ip)?))
+        }
+
+        _ => Ok(None),
+    }
+}
+
+/// Without `--auth-token` the terminal can be watched by anyone, but
+/// controlling it still requires a token, which is generated. When listening
+/// on a non-loopback interface, the token is required for everything, as if
+/// `--auth-token` was given without a value.
+fn build_auth(
+    mut auth_token: Option<String>,
+    read_only_token: Option<String>,
+    listen_addr: Option<SocketAddr>,
+) -> Result<api::auth::Auth> {
+    if listen_addr.is_some_and(|addr| !addr.ip().is_loopback()) {
+        auth_token.get_or_insert_with(String::new);
+    }
+
+    match resolve_token(auth_token, "auth token")? {
+        Some(token) => {
+            let read_only_token = resolve_token(read_only_token, "read-only token")?;
+
+            // the token would grant read-write access instead
+            if read_only_token.as_ref() == Some(&token) {
+                bail!("the read-only token must be different from the auth token");
+            }
+
+            Ok(api::auth::Auth::new(token, read_only_token, false))
+        }
+
+        None => {
+            let token = api::auth::generate_token().context("cannot generate token")?;
+
+            Ok(api::auth::Auth::new(token, None, true))
+        }
+    }
+}
+
+/// Generates the token when the option was given without a value.
+fn resolve_token(token: Option<String>, name: &str) -> Result<Option<String>> {
+    match token.as_deref() {
+        Some("") => {
+            let token = api::auth::generate_token().context("cannot generate token")?;
+            eprintln!("generated {name}: {token}");
+
+            Ok(Some(token))
+        }
+
+        _ => Ok(token),
+    }
+}
+
+fn start_socket_api(
+    path: Option<&Path>,
+    command_tx: mpsc::Sender<Request>,
+    clients_tx: mpsc::Sender<session::Client>,
+    sub: api::Subscription,
+) -> Result<()> {
+    if let Some(path) = path {
+        match std::fs::symlink_metadata(path) {
+            // a socket left by a previous run, which nobody listens on
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                if std::os::unix::net::UnixStream::connect(path).is_err() {
+                    std::fs::remove_file(path).with_context(|| {
+                        format!("cannot remove stale socket {}", path.display())
+                    })?;
+                }
+            }
+
+            Ok(_) => bail!("{} already exists and is not a socket", path.display()),
+
+            Err(_) => (),
+        }
+
+        // only the owner can connect, right from the start
+        let umask = stat::umask(stat::Mode::from_bits_truncate(0o177));
+        let listener = UnixListener::bind(path);
+        stat::umask(umask);
+
+        let listener =
+            listener.with_context(|| format!("cannot listen on socket {}", path.display()))?;
+
+        eprintln!("socket API listening on {}", path.display());
+        tokio::spawn(api::socket::start(listener, command_tx, clients_tx, sub));
     }
 
     Ok(())
 }
 
 async fn run_event_loop(
-    mut output_rx: mpsc::Receiver<Vec<u8>>,
-    input_tx: mpsc::Sender<Vec<u8>>,
-    mut command_rx: mpsc::Receiver<Command>,
+    source: Source,
+    keep_alive: bool,
+    mut command_rx: mpsc::Receiver<Request>,
     mut clients_rx: mpsc::Receiver<session::Client>,
     mut session: Session,
-    mut api_handle: JoinHandle<Result<()>>,
-) -> Result<()> {
+    process: Process,
+    api_handle: &mut JoinHandle<Result<()>>,
+) -> Result<(Session, Option<pty::ExitStatus>)> {
+    let mut process = Some(process);
+    let mut restarting: Option<(Reply, JoinHandle<Result<pty::ExitStatus>>)> = None;
     let mut serving = true;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
 
     loop {
+        let deadline = session.next_deadline();
+
         tokio::select! {
-            result = output_rx.recv() => {
+            result = recv_output(&mut process) => {
                 match result {
-                    Some(data) => {
+                    Some(pty::Output::Data(data)) => {
                         session.output(String::from_utf8_lossy(&data).to_string());
                     },
 
+                    Some(pty::Output::Resize(cols, rows)) => {
+                        session.resize(cols, rows);
+
+                        if let Some(reply) = process.as_mut().and_then(|p| p.pending_resizes.pop_front()) {
+                            session.ack(reply);
+                        }
+                    }
+
+                    Some(pty::Output::Signal(sig, result)) => {
+                        if let Some(reply) = process.as_mut().and_then(|p| p.pending_signals.pop_front()) {
+                            match result {
+                                Ok(()) => session.ack(reply),
+                                Err(e) => session.error(reply, format!("cannot send {sig}: {e}")),
+                            }
+                        }
+                    }
+
                     None => {
+                        let status = process.take().unwrap().stop().await?;
+                        session.exit(status);
+
+                        if keep_alive {
+                            eprintln!("process exited, keeping the session alive");
+                            continue;
+                        }
+
                         eprintln!("process exited, shutting down...");
-                        break;
+
+                        return Ok((session, Some(status)));
                     }
                 }
             }
 
-            command = command_rx.recv() => {
+            request = command_rx.recv() => {
+                let Some(Request { id, command, client }) = request else {
+                    eprintln!("stdin closed, shutting down...");
+                    break;
+                };
+
+                let reply = Reply { client, id };
+
                 match command {
-                    Some(Command::Input(seqs)) => {
-                        let data = command::seqs_to_bytes(&seqs, session.cursor_key_app_mode());
-                        input_tx.send(data).await?;
+                    Command::Input(_) | Command::Paste(_) | Command::Mouse(_) | Command::Signal(_, _)
+                        if session.is_controlled_by_other(client) =>
+                    {
+                        session.error(reply, "another client has taken control of the terminal");
                     }
 
-                    Some(Command::Snapshot) => {
-                        session.snapshot();
+                    Command::Input(seqs) => {
+                        match &process {
+                            Some(process) if process.is_replay() => {
+                                session.error(reply, "cannot send input while replaying a recording");
+                            }
+
+                            Some(process) => {
+                                let data = command::seqs_to_bytes(&seqs, session.cursor_key_app_mode());
+                                session.input(&data);
+                                process.input_tx.send(pty::Input::Data(data)).await?;
+                                session.ack(reply);
+                            }
+
+                            None => {
+                                session.error(reply, "no process running");
+                            }
+                        }
                     }
 
-                    Some(Command::Resize(cols, rows)) => {
-                        session.resize(cols, rows);
+                    Command::Paste(text) => {
+                        match &process {
+                            Some(process) if process.is_replay() => {
+                                session.error(reply, "cannot paste while replaying a recording");
+                            }
+
+                            Some(process) => {
+                                let data = command::paste_to_bytes(&text, session.modes().bracketed_paste);
+                                session.input(&data);
+                                process.input_tx.send(pty::Input::Data(data)).await?;
+                                session.ack(reply);
+                            }
+
+                            None => {
+                                session.error(reply, "no process running");
+                            }
+                        }
                     }
 
-                    None => {
-                        eprintln!("stdin closed, shutting down...");
-                        break;
+                    Command::Mouse(events) => {
+                        let modes = session.modes();
+
+                        match &process {
+                            Some(process) if process.is_replay() => {
+                                session.error(reply, "cannot send mouse events while replaying a recording");
+                            }
+
+                            Some(_) if modes.mouse_tracking == tracker::MouseTracking::None => {
+                                session.error(reply, "mouse tracking is not enabled");
+                            }
+
+                            Some(process) => {
+                                let data = command::mouse_to_bytes(&events, &modes);
+
+                                if !data.is_empty() {
+                                    session.input(&data);
+                                    process.input_tx.send(pty::Input::Data(data)).await?;
+                                }
+
+                                session.ack(reply);
+                            }
+
+                            None => {
+                                session.error(reply, "no process running");
+                            }
+                        }
+                    }
+
+                    Command::Signal(sig, target) => {
+                        match &mut process {
+                            Some(process) if process.is_replay() => {
+                                session.error(reply, "cannot send signals while replaying a recording");
+                            }
+
+                            Some(process) => {
+                                process.input_tx.send(pty::Input::Signal(sig, target)).await?;
+                                process.pending_signals.push_back(reply);
+                            }
+
+                            None => {
+                                session.error(reply, "no process running");
+                            }
+                        }
+                    }
+
+                    Command::Snapshot(format) => {
+                        session.snapshot(Some(reply), format);
+                    }
+
+                    Command::Screenshot => {
+                        session.screenshot(reply);
+                    }
+
+                    Command::Resize(cols, rows) => {
+                        match &mut process {
+                            Some(process) if process.is_replay() => {
+                                session.error(reply, "cannot resize while replaying a recording");
+                            }
+
+                            Some(process) => {
+                                process.input_tx.send(pty::Input::Resize(cols, rows)).await?;
+                                process.pending_resizes.push_back(reply);
+                            }
+
+                            None => {
+                                session.resize(cols, rows);
+                                session.ack(reply);
+                            }
+                        }
+                    }
+
+                    Command::WaitFor(wait) => {
+                        session.wait_for(reply, wait);
+                    }
+
+                    Command::GetScrollback(offset, limit, format) => {
+                        session.get_scrollback(reply, offset, limit, format);
+                    }
+
+                    Command::Seek(time) => {
+                        match process.as_ref().and_then(|p| p.seek_tx.as_ref()) {
+                            // not waiting for the replay to catch up, which
+                            // may itself be waiting for its output to be read
+                            Some(seek_tx) => match seek_tx.try_send(time) {
+                                Ok(()) => session.ack(reply),
+                                Err(_) => session.error(reply, "previous seek is still in progress"),
+                            },
+
+                            None => {
+                                session.error(reply, "seeking is only possible when replaying a recording");
+                            }
+                        }
+                    }
+
+                    Command::TakeControl => {
+                        session.take_control(reply);
+                    }
+
+                    Command::ReleaseControl => {
+                        session.release_control(reply);
+                    }
+
+                    Command::Restart => {
+                        if restarting.is_some() {
+                            session.error(reply, "process is already restarting");
+                        } else if let Some(process) = process.take() {
+                            // stopping may take a while, e.g. when the process
+                            // ignores SIGHUP, so it's done off the loop
+                            restarting = Some((reply, tokio::spawn(process.stop())));
+                        } else {
+                            process = restart_process(&source, keep_alive, &mut session, reply);
+                        }
                     }
                 }
             }
 
+            status = wait_stopped(&mut restarting) => {
+                let (reply, _) = restarting.take().unwrap();
+                session.exit(status?);
+                process = restart_process(&source, keep_alive, &mut session, reply);
+            }
+
             client = clients_rx.recv(), if serving => {
                 match client {
                     Some(client) => {
-                        client.accept(session.subscribe());
+                        session.serve(client);
                     }
 
                     None => {
@@ -125,12 +646,179 @@ async fn run_event_loop(
                 }
             }
 
-            _ = &mut api_handle => {
+            _ = sleep_until(deadline) => {
+                session.tick();
+            }
+
+            _ = &mut *api_handle => {
                 eprintln!("stdin closed, shutting down...");
                 break;
             }
+
+            _ = sigterm.recv() => {
+                eprintln!("terminated, shutting down...");
+                break;
+            }
+
+            _ = sigint.recv() => {
+                eprintln!("interrupted, shutting down...");
+                break;
+            }
         }
     }
 
-    Ok(())
+    if let Some(process) = process {
+        process.stop().await?;
+    }
+
+    if let Some((_, stopping)) = restarting {
+        stopping.await??;
+    }
+
+    Ok((session, None))
+}
+
+/// Starts the process anew, in the terminal of the current size.
+fn restart_process(
+    source: &Source,
+    keep_alive: bool,
+    session: &mut Session,
+    reply: Reply,
+) -> Option<Process> {
+    let (cols, rows) = session.size();
+    let size = cli::Size::new(cols as u16, rows as u16);
+
+    match start_process(source, &size, keep_alive) {
+        Ok((pid, process)) => {
+            session.restart(pid);
+            session.ack(reply);
+
+            Some(process)
+        }
+
+        Err(e) => {
+            session.error(reply, format!("cannot start process: {e}"));
+
+            None
+        }
+    }
+}
+
+async fn wait_stopped(
+    restarting: &mut Option<(Reply, JoinHandle<Result<pty::ExitStatus>>)>,
+) -> Result<pty::ExitStatus> {
+    match restarting {
+        Some((_, stopping)) => stopping.await?,
+        None => future::pending().await,
+    }
+}
+
+async fn recv_output(process: &mut Option<Process>) -> Option<pty::Output> {
+    match process {
+        Some(process) => process.output_rx.recv().await,
+        None => future::pending().await,
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{api, build_auth, cli, start_pty, start_socket_api};
+    use nix::sys::signal::Signal;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn stop_flooding_process() {
+        let (_, process) = start_pty("yes", &cli::Size::new(80, 24)).unwrap();
+
+        // nobody reads the output, so the channel fills up
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let result = tokio::time::timeout(Duration::from_secs(5), process.stop()).await;
+
+        assert!(result.expect("stopping shouldn't block").is_ok());
+    }
+
+    #[tokio::test]
+    async fn stop_process_ignoring_hup() {
+        let (_, process) = start_pty("trap '' HUP; sleep 100", &cli::Size::new(80, 24)).unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let result = tokio::time::timeout(Duration::from_secs(10), process.stop()).await;
+        let status = result.expect("stopping should kill the process").unwrap();
+
+        assert_eq!(status.code(), 128 + Signal::SIGKILL as i32);
+    }
+
+    #[tokio::test]
+    async fn stop_stopped_process() {
+        let (_, process) = start_pty("kill -STOP $$; sleep 100", &cli::Size::new(80, 24)).unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let result = tokio::time::timeout(Duration::from_secs(2), process.stop()).await;
+        let status = result
+            .expect("stopping should continue the process")
+            .unwrap();
+
+        assert_eq!(status.code(), 128 + Signal::SIGHUP as i32);
+    }
+
+    #[tokio::test]
+    async fn socket_path() {
+        let dir = std::env::temp_dir().join(format!("ht-socket-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ht.sock");
+        let start = || {
+            let (command_tx, _) = mpsc::channel(1);
+            let (clients_tx, _) = mpsc::channel(1);
+
+            start_socket_api(
+                Some(&path),
+                command_tx,
+                clients_tx,
+                api::Subscription::default(),
+            )
+        };
+
+        // not a socket, left alone
+        std::fs::write(&path, "data").unwrap();
+        assert!(start().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+        std::fs::remove_file(&path).unwrap();
+
+        start().unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // still listened on
+        assert!(start().is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn auth_for_listen_addr() {
+        let auth = build_auth(None, None, Some("127.0.0.1:8080".parse().unwrap())).unwrap();
+        assert!(auth.is_public_read());
+
+        let auth = build_auth(None, None, Some("0.0.0.0:8080".parse().unwrap())).unwrap();
+        assert!(!auth.is_public_read());
+        assert_eq!(auth.access(None), None);
+
+        let token = Some("s3cr3t".to_owned());
+        let auth = build_auth(token, None, Some("[::1]:8080".parse().unwrap())).unwrap();
+        assert!(!auth.is_public_read());
+        assert_eq!(auth.control_token(), "s3cr3t");
+
+        let (token, read_only_token) = (Some("s3cr3t".to_owned()), Some("s3cr3t".to_owned()));
+        assert!(build_auth(token, read_only_token, None).is_err());
+    }
 }
@@ -1,26 +1,71 @@
 use crate::nbio;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use nix::libc;
 use nix::pty;
 use nix::sys::signal::{self, SigHandler, Signal};
 use nix::sys::wait;
 use nix::unistd::{self, ForkResult, Pid};
+use serde::Deserialize;
 use std::env;
 use std::ffi::{CString, NulError};
 use std::fs::File;
 use std::future::Future;
 use std::io;
-use std::os::fd::FromRawFd;
 use std::os::fd::{AsRawFd, OwnedFd};
+use std::time::Duration;
 use tokio::io::unix::AsyncFd;
 use tokio::sync::mpsc;
 
+#[derive(Debug)]
+pub enum Input {
+    Data(Vec<u8>),
+    Signal(Signal, SignalTarget),
+    Resize(usize, usize),
+}
+
+#[derive(Debug)]
+pub enum Output {
+    Data(Vec<u8>),
+    /// Sent once the PTY has been resized, after all output produced at the
+    /// previous size.
+    Resize(usize, usize),
+    /// Result of sending the signal.
+    Signal(Signal, nix::Result<()>),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalTarget {
+    /// The top-level process started by ht
+    Process,
+    /// The terminal's foreground process group
+    #[default]
+    Group,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ExitStatus {
+    Exited(i32),
+    Signaled(Signal),
+}
+
+impl ExitStatus {
+    /// Exit code following the shell convention of 128 + signal number for
+    /// processes terminated by a signal.
+    pub fn code(&self) -> i32 {
+        match self {
+            ExitStatus::Exited(code) => *code,
+            ExitStatus::Signaled(signal) => 128 + *signal as i32,
+        }
+    }
+}
+
 pub fn spawn(
     command: String,
     winsize: &pty::Winsize,
-    input_rx: mpsc::Receiver<Vec<u8>>,
-    output_tx: mpsc::Sender<Vec<u8>>,
-) -> Result<(i32, impl Future<Output = Result<()>>)> {
+    input_rx: mpsc::Receiver<Input>,
+    output_tx: mpsc::Sender<Output>,
+) -> Result<(i32, impl Future<Output = Result<ExitStatus>>)> {
     let result = unsafe { pty::forkpty(Some(winsize), None) }?;
 
     match result.fork_result {
@@ -39,44 +84,98 @@ pub fn spawn(
 async fn drive_child(
     child: Pid,
     master: OwnedFd,
-    input_rx: mpsc::Receiver<Vec<u8>>,
-    output_tx: mpsc::Sender<Vec<u8>>,
-) -> Result<()> {
-    let result = do_drive_child(master, input_rx, output_tx).await;
+    input_rx: mpsc::Receiver<Input>,
+    output_tx: mpsc::Sender<Output>,
+) -> Result<ExitStatus> {
+    let result = do_drive_child(child, master, input_rx, output_tx).await;
     eprintln!("sending HUP signal to the child process");
-    unsafe { libc::kill(child.as_raw(), libc::SIGHUP) };
+    let _ = signal::kill(child, Signal::SIGHUP);
+    // a stopped process only handles the signal once continued
+    let _ = signal::kill(child, Signal::SIGCONT);
     eprintln!("waiting for the child process to exit");
 
-    tokio::task::spawn_blocking(move || {
-        let _ = wait::waitpid(child, None);
-    })
-    .await
-    .unwrap();
+    let mut waiting = tokio::task::spawn_blocking(move || wait::waitpid(child, None));
+
+    let status = match tokio::time::timeout(STOP_TIMEOUT, &mut waiting).await {
+        Ok(status) => status,
+
+        Err(_) => {
+            eprintln!("the child process didn't exit, sending KILL signal to its process group");
+            let _ = signal::killpg(child, Signal::SIGKILL);
+
+            waiting.await
+        }
+    }
+    .unwrap()?;
 
-    result
+    result?;
+
+    match status {
+        wait::WaitStatus::Exited(_, code) => Ok(ExitStatus::Exited(code)),
+        wait::WaitStatus::Signaled(_, signal, _) => Ok(ExitStatus::Signaled(signal)),
+        status => bail!("unexpected child status: {status:?}"),
+    }
 }
 
 const READ_BUF_SIZE: usize = 128 * 1024;
 
+/// How long the child process has to exit after SIGHUP before it's killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
 async fn do_drive_child(
+    child: Pid,
     master: OwnedFd,
-    mut input_rx: mpsc::Receiver<Vec<u8>>,
-    output_tx: mpsc::Sender<Vec<u8>>,
+    mut input_rx: mpsc::Receiver<Input>,
+    output_tx: mpsc::Sender<Output>,
 ) -> Result<()> {
     let mut buf = [0u8; READ_BUF_SIZE];
     let mut input: Vec<u8> = Vec::with_capacity(READ_BUF_SIZE);
     nbio::set_non_blocking(&master.as_raw_fd())?;
-    let mut master_file = unsafe { File::from_raw_fd(master.as_raw_fd()) };
-    let master_fd = AsyncFd::new(master)?;
+    let master_fd = AsyncFd::new(File::from(master))?;
 
     loop {
         tokio::select! {
             result = input_rx.recv() => {
                 match result {
-                    Some(data) => {
+                    Some(Input::Data(data)) => {
                         input.extend_from_slice(&data);
                     }
 
+                    Some(Input::Resize(cols, rows)) => {
+                        // flush output written at the old size first
+                        loop {
+                            match nbio::read(&mut master_fd.get_ref(), &mut buf)? {
+                                Some(0) => {
+                                    return Ok(());
+                                }
+
+                                Some(n) => {
+                                    if output_tx.send(Output::Data(buf[0..n].to_vec())).await.is_err() {
+                                        return Ok(());
+                                    }
+                                }
+
+                                None => {
+                                    break;
+                                }
+                            }
+                        }
+
+                        set_winsize(master_fd.get_ref(), cols, rows)?;
+
+                        if output_tx.send(Output::Resize(cols, rows)).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+
+                    Some(Input::Signal(sig, target)) => {
+                        let result = send_signal(child, master_fd.get_ref(), sig, target);
+
+                        if output_tx.send(Output::Signal(sig, result)).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+
                     None => {
                         return Ok(());
                     }
@@ -87,13 +186,16 @@ async fn do_drive_child(
                 let mut guard = result?;
 
                 loop {
-                    match nbio::read(&mut master_file, &mut buf)? {
+                    match nbio::read(&mut master_fd.get_ref(), &mut buf)? {
                         Some(0) => {
                             return Ok(());
                         }
 
                         Some(n) => {
-                            output_tx.send(buf[0..n].to_vec()).await?;
+                            // nobody reads the output anymore, e.g. when stopping
+                            if output_tx.send(Output::Data(buf[0..n].to_vec())).await.is_err() {
+                                return Ok(());
+                            }
                         }
 
                         None => {
@@ -109,7 +211,7 @@ async fn do_drive_child(
                 let mut buf: &[u8] = input.as_ref();
 
                 loop {
-                    match nbio::write(&mut master_file, buf)? {
+                    match nbio::write(&mut master_fd.get_ref(), buf)? {
                         Some(0) => {
                             return Ok(());
                         }
@@ -141,6 +243,30 @@ async fn do_drive_child(
     }
 }
 
+/// Resizes the terminal. The kernel notifies the foreground process group of
+/// the change with SIGWINCH.
+fn set_winsize(master: &File, cols: usize, rows: usize) -> io::Result<()> {
+    let winsize = pty::Winsize {
+        ws_col: cols as u16,
+        ws_row: rows as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+
+    if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn send_signal(child: Pid, master: &File, sig: Signal, target: SignalTarget) -> nix::Result<()> {
+    match target {
+        SignalTarget::Process => signal::kill(child, sig),
+        SignalTarget::Group => signal::killpg(unistd::tcgetpgrp(master)?, sig),
+    }
+}
+
 fn exec(command: String) -> io::Result<()> {
     let command = ["/bin/sh".to_owned(), "-c".to_owned(), command]
         .iter()
@@ -0,0 +1,68 @@
+use crate::asciicast;
+use crate::session::Event;
+use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// Records the session to an asciicast v2 file, until the session ends.
+pub async fn start(
+    mut file: File,
+    record_input: bool,
+    mut events: mpsc::UnboundedReceiver<Event>,
+) -> Result<()> {
+    let mut start_time = 0.0;
+
+    while let Some(event) = events.recv().await {
+        let event = match event {
+            Event::Init(time, cols, rows, _pid, seq, _text, _cursor, _modes, _driver) => {
+                start_time = time;
+
+                let header = asciicast::Header {
+                    cols,
+                    rows,
+                    timestamp: unix_time(),
+                };
+
+                write(&mut file, header.to_json()).await?;
+
+                // terminal state from before the recording started
+                asciicast::Event::Output(0.0, seq)
+            }
+
+            Event::Output(time, data) => {
+                asciicast::Event::Output((time - start_time).max(0.0), data)
+            }
+
+            Event::Input(time, data) if record_input => {
+                asciicast::Event::Input((time - start_time).max(0.0), data)
+            }
+
+            Event::Resize(time, cols, rows) => {
+                asciicast::Event::Resize((time - start_time).max(0.0), cols, rows)
+            }
+
+            _ => continue,
+        };
+
+        write(&mut file, event.to_json()).await?;
+    }
+
+    file.flush().await?;
+
+    Ok(())
+}
+
+async fn write(file: &mut File, value: serde_json::Value) -> Result<()> {
+    file.write_all(format!("{value}\n").as_bytes()).await?;
+
+    Ok(())
+}
+
+fn unix_time() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
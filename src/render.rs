@@ -0,0 +1,131 @@
+pub mod font;
+pub mod html;
+mod raster;
+pub mod styled;
+pub mod svg;
+pub mod theme;
+use serde::Deserialize;
+use theme::Theme;
+
+/// Fonts for the formats rendered by the viewer, i.e. SVG and HTML.
+const FONT_FAMILY: &str = "'JetBrains Mono', 'Cascadia Code', 'Fira Code', Menlo, Consolas, 'DejaVu Sans Mono', monospace";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Text,
+    Styled,
+    Svg,
+    Html,
+}
+
+/// Formats of standalone images of the terminal view.
+#[derive(Debug, Clone, Copy)]
+pub enum Image {
+    Svg,
+    Png,
+    Html,
+}
+
+#[derive(Clone)]
+pub enum Rendering {
+    Styled(Vec<styled::Line>),
+    Svg(String),
+    Html(String),
+}
+
+pub fn render(vt: &avt::Vt, format: Format, theme: &Theme) -> Option<Rendering> {
+    render_lines(vt.view(), cursor(vt), format, theme)
+}
+
+pub fn render_lines(
+    lines: &[avt::Line],
+    cursor: Option<(usize, usize)>,
+    format: Format,
+    theme: &Theme,
+) -> Option<Rendering> {
+    match format {
+        Format::Text => None,
+        Format::Styled => Some(Rendering::Styled(styled::lines(lines))),
+        Format::Svg => Some(Rendering::Svg(svg::render(lines, cursor, theme))),
+        Format::Html => Some(Rendering::Html(html::render(lines, cursor, theme))),
+    }
+}
+
+/// Renders the terminal view as a standalone image of the given format.
+pub fn image(vt: &avt::Vt, image: Image, theme: &Theme) -> Vec<u8> {
+    match image {
+        Image::Svg => svg(vt, theme).into_bytes(),
+        Image::Png => png(vt.view(), cursor(vt), theme),
+        Image::Html => html(vt, theme).into_bytes(),
+    }
+}
+
+/// Renders the terminal view as a standalone SVG image.
+pub fn svg(vt: &avt::Vt, theme: &Theme) -> String {
+    svg::render(vt.view(), cursor(vt), theme)
+}
+
+/// Renders the terminal view as a standalone HTML document.
+pub fn html(vt: &avt::Vt, theme: &Theme) -> String {
+    html::document(&html::render(vt.view(), cursor(vt), theme), theme)
+}
+
+/// Renders lines of the terminal as a PNG image. Rasterizing takes a while,
+/// and so may loading the fallback fonts the first time, so it's best done on
+/// a blocking thread.
+pub fn png(lines: &[avt::Line], cursor: Option<(usize, usize)>, theme: &Theme) -> Vec<u8> {
+    raster::render(lines, cursor, theme).to_png()
+}
+
+pub fn cursor(vt: &avt::Vt) -> Option<(usize, usize)> {
+    let cursor = vt.cursor();
+
+    cursor.visible.then_some((cursor.col, cursor.row))
+}
+
+/// Escapes text for SVG and HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Column of the cursor on the given line, if it's there. The cursor can be
+/// just past the last column, waiting to wrap, in which case it's shown on
+/// the last cell.
+fn cursor_col(line: &avt::Line, row: usize, cursor: Option<(usize, usize)>) -> Option<usize> {
+    cursor
+        .filter(|(_, r)| *r == row)
+        .map(|(c, _)| c.min(line.len().saturating_sub(1)))
+}
+
+impl Image {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Image::Svg => "image/svg+xml",
+            Image::Png => "image/png",
+            Image::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+impl Rendering {
+    pub fn to_json(&self) -> (&'static str, serde_json::Value) {
+        match self {
+            Rendering::Styled(lines) => ("lines", serde_json::json!(lines)),
+            Rendering::Svg(svg) => ("svg", serde_json::json!(svg)),
+            Rendering::Html(html) => ("html", serde_json::json!(html)),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Fonts for rendered images: the embedded DejaVu Sans Mono, followed by
+//! fallback fonts for the characters it lacks, e.g. CJK ideographs, which are
+//! either given by the user or looked up among the system fonts.
+
+use ab_glyph::{Font, FontArc, FontVec};
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const FONT_DATA: &[u8] = include_bytes!("fonts/DejaVuSansMono.ttf");
+
+/// Directories searched for a fallback font, besides the ones in the home
+/// directory.
+const FONT_DIRS: [&str; 4] = [
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    "/System/Library/Fonts",
+    "/Library/Fonts",
+];
+
+/// File name fragments of common fonts covering CJK ideographs.
+const CJK_FONT_NAMES: [&str; 16] = [
+    "CJK",
+    "SourceHan",
+    "WenQuanYi",
+    "wqy",
+    "DroidSansFallback",
+    "NotoSansSC",
+    "NotoSansTC",
+    "NotoSansJP",
+    "NotoSansKR",
+    "Sarasa",
+    "PingFang",
+    "Hiragino",
+    "ukai",
+    "uming",
+    "ipag",
+    "unifont",
+];
+
+/// A character the fallback font found among the system fonts must have.
+const CJK_PROBE: char = '\u{4e2d}';
+
+/// How deep font directories are searched.
+const MAX_DEPTH: usize = 4;
+
+static FALLBACKS: OnceLock<Vec<FontArc>> = OnceLock::new();
+
+/// Uses the given font files as fallbacks, instead of looking one up among
+/// the system fonts.
+pub fn set_fallbacks(paths: &[PathBuf]) -> Result<()> {
+    let fonts = paths
+        .iter()
+        .map(|path| load(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let _ = FALLBACKS.set(fonts);
+
+    Ok(())
+}
+
+/// The embedded font, followed by the fallback fonts. Unless given, these are
+/// looked up the first time, which is slow, so it's done by the blocking
+/// thread rendering the image.
+pub fn fonts() -> Vec<FontArc> {
+    let fallbacks = FALLBACKS.get_or_init(|| find_fallback().into_iter().collect());
+
+    [primary()]
+        .into_iter()
+        .chain(fallbacks.iter().cloned())
+        .collect()
+}
+
+pub fn primary() -> FontArc {
+    FontArc::try_from_slice(FONT_DATA).expect("embedded font should be valid")
+}
+
+/// Loads a TrueType or OpenType font file, or the first font of a collection.
+fn load(path: &Path) -> Result<FontArc> {
+    let data = fs::read(path).with_context(|| format!("cannot read font {}", path.display()))?;
+
+    let font = FontVec::try_from_vec_and_index(data, 0)
+        .with_context(|| format!("invalid font {}", path.display()))?;
+
+    Ok(FontArc::new(font))
+}
+
+fn find_fallback() -> Option<FontArc> {
+    let mut dirs: Vec<PathBuf> = FONT_DIRS.iter().map(PathBuf::from).collect();
+
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+    }
+
+    let mut files = Vec::new();
+
+    for dir in &dirs {
+        find_font_files(dir, 0, &mut files);
+    }
+
+    files.sort();
+
+    files
+        .iter()
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            CJK_FONT_NAMES.iter().any(|n| name.contains(n))
+        })
+        .filter_map(|path| load(path).ok())
+        .find(|font| font.glyph_id(CJK_PROBE).0 != 0)
+}
+
+fn find_font_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                find_font_files(&path, depth + 1, files);
+            }
+        } else if path.extension().is_some_and(|ext| {
+            ["ttf", "otf", "ttc"]
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        }) {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::primary;
+    use ab_glyph::Font;
+
+    #[test]
+    fn embedded_font() {
+        let font = primary();
+
+        assert_eq!(font.units_per_em(), Some(2048.0));
+        assert_ne!(font.glyph_id('A'), font.glyph_id('B'));
+        assert_ne!(font.glyph_id('─').0, 0);
+        assert_eq!(font.glyph_id('\u{4e2d}').0, 0);
+    }
+}
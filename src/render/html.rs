@@ -0,0 +1,118 @@
+use super::theme::{Style, Theme};
+use super::{escape, FONT_FAMILY};
+
+/// Renders lines of the terminal as a `<pre>` element with styled `<span>`s,
+/// with the cursor (col, row) shown as an inverted cell. All styles are
+/// inline, so the element can be embedded in any page as is.
+pub fn render(lines: &[avt::Line], cursor: Option<(usize, usize)>, theme: &Theme) -> String {
+    let mut html = format!(
+        r#"<pre class="ht-view" style="margin:0;padding:10px;line-height:1.2;font-family:{FONT_FAMILY};color:{};background-color:{}">"#,
+        theme.fg, theme.bg
+    );
+
+    for (row, line) in lines.iter().enumerate() {
+        let cursor_col = super::cursor_col(line, row, cursor);
+
+        let mut runs: Vec<(Style, String)> = Vec::new();
+
+        for (col, cell) in line.cells().iter().enumerate() {
+            // the second half of a wide character
+            if cell.width() == 0 {
+                continue;
+            }
+
+            let style = theme.style(cell.pen(), cursor_col == Some(col));
+
+            match runs.last_mut() {
+                Some((s, text)) if *s == style => text.push(cell.char()),
+                _ => runs.push((style, cell.char().to_string())),
+            }
+        }
+
+        if row > 0 {
+            html.push('\n');
+        }
+
+        for (style, text) in runs {
+            let css = css(&style, theme);
+
+            if css.is_empty() {
+                html.push_str(&escape(&text));
+            } else {
+                html.push_str(&format!(r#"<span style="{css}">{}</span>"#, escape(&text)));
+            }
+        }
+    }
+
+    html.push_str("</pre>");
+
+    html
+}
+
+/// Wraps the rendered view in a standalone HTML document.
+pub fn document(pre: &str, theme: &Theme) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"UTF-8\">\n<title>Snapshot - ht</title>\n</head>\n<body style=\"margin:0;background-color:{}\">\n{pre}\n</body>\n</html>\n",
+        theme.bg
+    )
+}
+
+fn css(style: &Style, theme: &Theme) -> String {
+    let mut css = Vec::new();
+
+    if style.fg != theme.fg {
+        css.push(format!("color:{}", style.fg));
+    }
+
+    if let Some(bg) = style.bg {
+        css.push(format!("background-color:{bg}"));
+    }
+
+    if style.bold {
+        css.push("font-weight:bold".to_owned());
+    }
+
+    if style.faint {
+        css.push("opacity:0.5".to_owned());
+    }
+
+    if style.italic {
+        css.push("font-style:italic".to_owned());
+    }
+
+    match (style.underline, style.strikethrough) {
+        (true, true) => css.push("text-decoration:underline line-through".to_owned()),
+        (true, false) => css.push("text-decoration:underline".to_owned()),
+        (false, true) => css.push("text-decoration:line-through".to_owned()),
+        (false, false) => (),
+    }
+
+    css.join(";")
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+    use crate::render::theme::Theme;
+
+    #[test]
+    fn styled_text() {
+        let mut vt = avt::Vt::new(8, 2);
+        vt.feed_str("a<b \x1b[1;31mred\x1b[0m\r\n\x1b[3;44mbg\x1b[0m");
+        let html = render(vt.view(), Some((2, 1)), &Theme::default());
+        let body = &html[html.find('>').unwrap() + 1..];
+
+        assert!(html.starts_with(r#"<pre class="ht-view" style="margin:0;"#));
+        assert!(html.contains("color:#cccccc;background-color:#121314\">"));
+
+        assert_eq!(
+            body,
+            concat!(
+                r#"a&lt;b <span style="color:#dd3c69;font-weight:bold">red</span> "#,
+                "\n",
+                r#"<span style="background-color:#26b0d7;font-style:italic">bg</span>"#,
+                r#"<span style="color:#121314;background-color:#cccccc"> </span>     </pre>"#
+            )
+        );
+    }
+}
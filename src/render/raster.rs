@@ -0,0 +1,698 @@
+use super::font;
+use super::theme::{Rgb, Style, Theme};
+use ab_glyph::{point, Font, FontArc, GlyphId, OutlineCurve};
+use ab_glyph_rasterizer::Rasterizer;
+use std::collections::HashMap;
+
+const FONT_SIZE: f32 = 15.0;
+const PADDING: usize = 10;
+const ITALIC_SHEAR: f32 = 0.2;
+
+/// An 8-bit RGB image.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .expect("encoding to memory should succeed");
+
+        data
+    }
+}
+
+/// Renders lines of the terminal to an image, using the embedded DejaVu Sans
+/// Mono font, with the cursor (col, row) drawn as a block. Box drawing and
+/// block characters are drawn as shapes, so that they connect seamlessly.
+pub fn render(lines: &[avt::Line], cursor: Option<(usize, usize)>, theme: &Theme) -> Image {
+    render_with_fonts(lines, cursor, theme, &font::fonts())
+}
+
+/// Renders with the given fonts, the first of which sets the cell size.
+/// Characters missing from it are drawn with the first of the others which
+/// has them, or as an outlined box.
+fn render_with_fonts(
+    lines: &[avt::Line],
+    cursor: Option<(usize, usize)>,
+    theme: &Theme,
+    fonts: &[FontArc],
+) -> Image {
+    let font = &fonts[0];
+    let scale = scale(font);
+    let cell_width = (font.h_advance_unscaled(font.glyph_id('M')) * scale).round() as usize;
+    let cell_height =
+        ((font.ascent_unscaled() - font.descent_unscaled() + font.line_gap_unscaled()) * scale)
+            .round() as usize;
+    let baseline = (font.ascent_unscaled() * scale).round();
+    let cols = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+
+    let mut canvas = Canvas {
+        image: Image {
+            width: cols * cell_width + 2 * PADDING,
+            height: lines.len() * cell_height + 2 * PADDING,
+            pixels: Vec::new(),
+        },
+        cell_width,
+        cell_height,
+    };
+
+    canvas.image.pixels =
+        [theme.bg.0, theme.bg.1, theme.bg.2].repeat(canvas.image.width * canvas.image.height);
+    let mut glyphs = Glyphs::new(fonts, baseline, cell_width, cell_height);
+
+    for (row, line) in lines.iter().enumerate() {
+        let cursor_col = super::cursor_col(line, row, cursor);
+
+        for (col, cell) in line.cells().iter().enumerate() {
+            // the second half of a wide character
+            if cell.width() == 0 {
+                continue;
+            }
+
+            let style = theme.style(cell.pen(), cursor_col == Some(col));
+            let width = cell.width();
+
+            if let Some(bg) = style.bg {
+                canvas.fill(col, row, width, (0.0, 0.0, 1.0, 1.0), bg, 1.0);
+            }
+
+            let fg = if style.faint {
+                blend(style.fg, style.bg.unwrap_or(theme.bg), 0.5)
+            } else {
+                style.fg
+            };
+
+            let c = cell.char();
+
+            if !draw_box(&mut canvas, col, row, c, fg) {
+                if let Some(coverage) = glyphs.get(c, width, style.italic) {
+                    canvas.draw(col, row, width, coverage, fg, style.bold);
+                } else if c != ' ' {
+                    canvas.draw_missing(col, row, width, fg);
+                }
+            }
+
+            draw_decorations(&mut canvas, col, row, width, &style, fg, baseline);
+        }
+    }
+
+    canvas.image
+}
+
+struct Canvas {
+    image: Image,
+    cell_width: usize,
+    cell_height: usize,
+}
+
+impl Canvas {
+    fn cell_origin(&self, col: usize, row: usize) -> (usize, usize) {
+        (
+            PADDING + col * self.cell_width,
+            PADDING + row * self.cell_height,
+        )
+    }
+
+    fn blend_pixel(&mut self, x: usize, y: usize, color: Rgb, alpha: f32) {
+        if x >= self.image.width || y >= self.image.height || alpha <= 0.0 {
+            return;
+        }
+
+        let i = (y * self.image.width + x) * 3;
+        let pixel = &mut self.image.pixels[i..i + 3];
+        let Rgb(r, g, b) = blend(color, Rgb(pixel[0], pixel[1], pixel[2]), alpha.min(1.0));
+        pixel.copy_from_slice(&[r, g, b]);
+    }
+
+    /// Fills a rectangle (x0, y0, x1, y1) given in fractions of the cell(s)
+    /// size.
+    fn fill(
+        &mut self,
+        col: usize,
+        row: usize,
+        width: usize,
+        (x0, y0, x1, y1): (f32, f32, f32, f32),
+        color: Rgb,
+        alpha: f32,
+    ) {
+        let (ox, oy) = self.cell_origin(col, row);
+        let w = (self.cell_width * width) as f32;
+        let h = self.cell_height as f32;
+        let (x0, x1) = ((x0 * w).round() as usize, (x1 * w).round() as usize);
+        let (y0, y1) = ((y0 * h).round() as usize, (y1 * h).round() as usize);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend_pixel(ox + x, oy + y, color, alpha);
+            }
+        }
+    }
+
+    /// Fills a rectangle (x, y, width, height) given in pixels, relative to
+    /// the cell's origin.
+    fn fill_px(
+        &mut self,
+        col: usize,
+        row: usize,
+        (x, y, w, h): (usize, usize, usize, usize),
+        color: Rgb,
+    ) {
+        let (ox, oy) = self.cell_origin(col, row);
+
+        for py in y..y + h {
+            for px in x..x + w {
+                self.blend_pixel(ox + px, oy + py, color, 1.0);
+            }
+        }
+    }
+
+    fn draw(
+        &mut self,
+        col: usize,
+        row: usize,
+        width: usize,
+        coverage: &[f32],
+        color: Rgb,
+        bold: bool,
+    ) {
+        let (ox, oy) = self.cell_origin(col, row);
+        let w = self.cell_width * width;
+
+        for y in 0..self.cell_height {
+            for x in 0..w {
+                let mut alpha = coverage[y * w + x];
+
+                // faux bold: the glyph is drawn again, shifted by a pixel
+                if bold && x > 0 {
+                    alpha = alpha.max(coverage[y * w + x - 1]);
+                }
+
+                self.blend_pixel(ox + x, oy + y, color, alpha);
+            }
+        }
+    }
+
+    fn draw_missing(&mut self, col: usize, row: usize, width: usize, color: Rgb) {
+        let w = self.cell_width * width;
+        let h = self.cell_height;
+        let (x0, y0, x1, y1) = (1, h / 5, w - 2, h - h / 5);
+
+        self.fill_px(col, row, (x0, y0, x1 - x0 + 1, 1), color);
+        self.fill_px(col, row, (x0, y1, x1 - x0 + 1, 1), color);
+        self.fill_px(col, row, (x0, y0, 1, y1 - y0), color);
+        self.fill_px(col, row, (x1, y0, 1, y1 - y0), color);
+    }
+}
+
+/// Rasterized glyphs, by character, width in cells and slant.
+struct Glyphs<'a> {
+    fonts: &'a [FontArc],
+    baseline: f32,
+    cell_width: usize,
+    cell_height: usize,
+    cache: HashMap<(char, usize, bool), Option<Vec<f32>>>,
+}
+
+impl<'a> Glyphs<'a> {
+    fn new(fonts: &'a [FontArc], baseline: f32, cell_width: usize, cell_height: usize) -> Self {
+        Self {
+            fonts,
+            baseline,
+            cell_width,
+            cell_height,
+            cache: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, c: char, width: usize, italic: bool) -> Option<&[f32]> {
+        let fonts = self.fonts;
+        let (cell_width, cell_height, baseline) =
+            (self.cell_width, self.cell_height, self.baseline);
+
+        self.cache
+            .entry((c, width, italic))
+            .or_insert_with(|| {
+                let (font, id) = fonts.iter().find_map(|font| {
+                    let id = font.glyph_id(c);
+
+                    (id.0 != 0).then_some((font, id))
+                })?;
+
+                let w = cell_width * width;
+                let advance = font.h_advance_unscaled(id) * scale(font);
+                // wide characters are centered in their two cells
+                let x = ((w as f32 - advance) / 2.0).max(0.0);
+                let shear = if italic { ITALIC_SHEAR } else { 0.0 };
+
+                Some(rasterize(font, id, (x, baseline), shear, (w, cell_height)))
+            })
+            .as_deref()
+    }
+}
+
+/// Scale factor from font units to pixels.
+fn scale(font: &FontArc) -> f32 {
+    FONT_SIZE / font.units_per_em().unwrap_or(1000.0)
+}
+
+/// Rasterizes a glyph into a coverage map of `width` x `height` pixels, with
+/// the glyph's origin (on the baseline) at `origin`. `shear` slants the glyph
+/// to the right, e.g. for italics.
+fn rasterize(
+    font: &FontArc,
+    id: GlyphId,
+    (x, baseline): (f32, f32),
+    shear: f32,
+    (width, height): (usize, usize),
+) -> Vec<f32> {
+    let scale = scale(font);
+    let mut rasterizer = Rasterizer::new(width, height);
+    let mut coverage = vec![0.0; width * height];
+
+    let transform = |p: ab_glyph::Point| {
+        let y = p.y * scale;
+
+        // clamped, as parts outside of the cells would wrap to other rows
+        point(
+            (x + p.x * scale + y * shear).clamp(0.0, width as f32),
+            (baseline - y).clamp(0.0, height as f32),
+        )
+    };
+
+    if let Some(outline) = font.outline(id) {
+        for curve in outline.curves {
+            match curve {
+                OutlineCurve::Line(p0, p1) => rasterizer.draw_line(transform(p0), transform(p1)),
+
+                OutlineCurve::Quad(p0, p1, p2) => {
+                    rasterizer.draw_quad(transform(p0), transform(p1), transform(p2))
+                }
+
+                OutlineCurve::Cubic(p0, p1, p2, p3) => rasterizer.draw_cubic(
+                    transform(p0),
+                    transform(p1),
+                    transform(p2),
+                    transform(p3),
+                ),
+            }
+        }
+    }
+
+    rasterizer.for_each_pixel(|i, alpha| coverage[i] = alpha);
+
+    coverage
+}
+
+fn draw_decorations(
+    canvas: &mut Canvas,
+    col: usize,
+    row: usize,
+    width: usize,
+    style: &Style,
+    fg: Rgb,
+    baseline: f32,
+) {
+    let w = canvas.cell_width * width;
+    let baseline = baseline as usize;
+
+    if style.underline {
+        let y = (baseline + 2).min(canvas.cell_height - 1);
+        canvas.fill_px(col, row, (0, y, w, 1), fg);
+    }
+
+    if style.strikethrough {
+        canvas.fill_px(col, row, (0, baseline * 2 / 3, w, 1), fg);
+    }
+}
+
+/// Line weights of the arms of a box drawing character, going left, right,
+/// up and down.
+#[derive(Clone, Copy, PartialEq)]
+enum Weight {
+    None,
+    Light,
+    Heavy,
+    Double,
+}
+
+fn box_arms(c: char) -> Option<[Weight; 4]> {
+    use Weight::*;
+
+    let (l, h, d) = (Light, Heavy, Double);
+    let n = None;
+
+    let arms = match c {
+        '─' | '┄' | '┈' | '╌' => [l, l, n, n],
+        '━' | '┅' | '┉' | '╍' => [h, h, n, n],
+        '│' | '┆' | '┊' | '╎' => [n, n, l, l],
+        '┃' | '┇' | '┋' | '╏' => [n, n, h, h],
+        '┌' | '╭' => [n, l, n, l],
+        '┐' | '╮' => [l, n, n, l],
+        '└' | '╰' => [n, l, l, n],
+        '┘' | '╯' => [l, n, l, n],
+        '├' => [n, l, l, l],
+        '┤' => [l, n, l, l],
+        '┬' => [l, l, n, l],
+        '┴' => [l, l, l, n],
+        '┼' => [l, l, l, l],
+        '┏' => [n, h, n, h],
+        '┓' => [h, n, n, h],
+        '┗' => [n, h, h, n],
+        '┛' => [h, n, h, n],
+        '┣' => [n, h, h, h],
+        '┫' => [h, n, h, h],
+        '┳' => [h, h, n, h],
+        '┻' => [h, h, h, n],
+        '╋' => [h, h, h, h],
+        '═' => [d, d, n, n],
+        '║' => [n, n, d, d],
+        '╔' => [n, d, n, d],
+        '╗' => [d, n, n, d],
+        '╚' => [n, d, d, n],
+        '╝' => [d, n, d, n],
+        '╠' => [n, d, d, d],
+        '╣' => [d, n, d, d],
+        '╦' => [d, d, n, d],
+        '╩' => [d, d, d, n],
+        '╬' => [d, d, d, d],
+        '╴' => [l, n, n, n],
+        '╵' => [n, n, l, n],
+        '╶' => [n, l, n, n],
+        '╷' => [n, n, n, l],
+        '╸' => [h, n, n, n],
+        '╹' => [n, n, h, n],
+        '╺' => [n, h, n, n],
+        '╻' => [n, n, n, h],
+        _ => return Option::None,
+    };
+
+    Some(arms)
+}
+
+/// Draws box drawing and block element characters, returning false for
+/// other characters.
+fn draw_box(canvas: &mut Canvas, col: usize, row: usize, c: char, color: Rgb) -> bool {
+    if let Some(arms) = box_arms(c) {
+        draw_arms(canvas, col, row, arms, color);
+
+        return true;
+    }
+
+    let eighth = |n: u32| n as f32 / 8.0;
+
+    match c as u32 {
+        // upper half block
+        0x2580 => canvas.fill(col, row, 1, (0.0, 0.0, 1.0, 0.5), color, 1.0),
+
+        // lower one eighth block to full block
+        n @ 0x2581..=0x2588 => canvas.fill(
+            col,
+            row,
+            1,
+            (0.0, 1.0 - eighth(n - 0x2580), 1.0, 1.0),
+            color,
+            1.0,
+        ),
+
+        // left seven eighths block to left one eighth block
+        n @ 0x2589..=0x258f => {
+            canvas.fill(col, row, 1, (0.0, 0.0, eighth(0x2590 - n), 1.0), color, 1.0)
+        }
+
+        // right half block
+        0x2590 => canvas.fill(col, row, 1, (0.5, 0.0, 1.0, 1.0), color, 1.0),
+
+        // light, medium and dark shade
+        n @ 0x2591..=0x2593 => {
+            let alpha = (n - 0x2590) as f32 / 4.0;
+            canvas.fill(col, row, 1, (0.0, 0.0, 1.0, 1.0), color, alpha);
+        }
+
+        // upper one eighth block
+        0x2594 => canvas.fill(col, row, 1, (0.0, 0.0, 1.0, eighth(1)), color, 1.0),
+
+        // right one eighth block
+        0x2595 => canvas.fill(col, row, 1, (1.0 - eighth(1), 0.0, 1.0, 1.0), color, 1.0),
+
+        _ => return false,
+    }
+
+    true
+}
+
+fn draw_arms(canvas: &mut Canvas, col: usize, row: usize, arms: [Weight; 4], color: Rgb) {
+    let (w, h) = (canvas.cell_width, canvas.cell_height);
+    let light = (w / 8).max(1);
+    let (cx, cy) = (w / 2, h / 2);
+    let [left, right, up, down] = arms;
+
+    // bands across the cell (offset, thickness) for a line of given weight
+    let bands = |weight: Weight, center: usize| -> Vec<(usize, usize)> {
+        match weight {
+            Weight::None => vec![],
+            Weight::Light => vec![(center - light / 2, light)],
+            Weight::Heavy => vec![(center - light, light * 2 + 1)],
+            Weight::Double => vec![
+                (center - light - light / 2, light),
+                (center + light - light / 2, light),
+            ],
+        }
+    };
+
+    // horizontal arms reach past the center to cover the vertical lines'
+    // width, and the other way round
+    let vertical_extent = bands(up, cx)
+        .into_iter()
+        .chain(bands(down, cx))
+        .fold((cx, cx + 1), |(a, b), (o, t)| (a.min(o), b.max(o + t)));
+    let horizontal_extent = bands(left, cy)
+        .into_iter()
+        .chain(bands(right, cy))
+        .fold((cy, cy + 1), |(a, b), (o, t)| (a.min(o), b.max(o + t)));
+
+    for (y, t) in bands(left, cy) {
+        canvas.fill_px(col, row, (0, y, vertical_extent.1, t), color);
+    }
+
+    for (y, t) in bands(right, cy) {
+        canvas.fill_px(
+            col,
+            row,
+            (vertical_extent.0, y, w - vertical_extent.0, t),
+            color,
+        );
+    }
+
+    for (x, t) in bands(up, cx) {
+        canvas.fill_px(col, row, (x, 0, t, horizontal_extent.1), color);
+    }
+
+    for (x, t) in bands(down, cx) {
+        canvas.fill_px(
+            col,
+            row,
+            (x, horizontal_extent.0, t, h - horizontal_extent.0),
+            color,
+        );
+    }
+}
+
+fn blend(fg: Rgb, bg: Rgb, alpha: f32) -> Rgb {
+    let mix = |a: u8, b: u8| (a as f32 * alpha + b as f32 * (1.0 - alpha)).round() as u8;
+
+    Rgb(mix(fg.0, bg.0), mix(fg.1, bg.1), mix(fg.2, bg.2))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{font, rasterize, render, render_with_fonts};
+    use crate::render::theme::{Rgb, Theme};
+    use ab_glyph::{Font, FontArc};
+
+    fn pixel(image: &super::Image, x: usize, y: usize) -> Rgb {
+        let i = (y * image.width + x) * 3;
+
+        Rgb(image.pixels[i], image.pixels[i + 1], image.pixels[i + 2])
+    }
+
+    #[test]
+    fn size_and_background() {
+        let theme = Theme::default();
+        let vt = avt::Vt::new(4, 2);
+        let image = render(vt.view(), None, &theme);
+
+        assert_eq!((image.width, image.height), (4 * 9 + 20, 2 * 17 + 20));
+        assert_eq!(image.pixels.len(), image.width * image.height * 3);
+        assert!(image.pixels.chunks(3).all(|p| p == [0x12, 0x13, 0x14]));
+    }
+
+    #[test]
+    fn text_and_cursor() {
+        let theme = Theme::default();
+        let mut vt = avt::Vt::new(4, 1);
+        vt.feed_str("\x1b[41mA\x1b[0m");
+        let image = render(vt.view(), Some((2, 0)), &theme);
+
+        // background of the first cell, top left corner
+        assert_eq!(pixel(&image, 10, 10), theme.palette[1]);
+        // the cursor block
+        assert_eq!(pixel(&image, 10 + 2 * 9 + 1, 11), theme.fg);
+        // some ink of the glyph
+        assert!((10..19).any(|x| (10..27).any(|y| pixel(&image, x, y) == theme.fg)));
+    }
+
+    #[test]
+    fn box_drawing() {
+        let theme = Theme::default();
+        let mut vt = avt::Vt::new(3, 1);
+        vt.feed_str("─┼█");
+        let image = render(vt.view(), None, &theme);
+        let row: Vec<Rgb> = (10..10 + 27).map(|x| pixel(&image, x, 10 + 8)).collect();
+
+        // horizontal lines connect across cells
+        assert!(row.iter().all(|&p| p == theme.fg));
+    }
+
+    #[test]
+    fn wide_chars() {
+        let theme = Theme::default();
+        let fonts = [font::primary(), FontArc::try_from_vec(test_font()).unwrap()];
+        let mut vt = avt::Vt::new(4, 1);
+        vt.feed_str("\u{4e2d}\u{4e00}");
+        let image = render_with_fonts(vt.view(), None, &theme, &fonts);
+
+        // drawn with the fallback font, centered in both cells
+        assert_eq!(pixel(&image, 10 + 9, 10 + 8), theme.fg);
+        assert_ne!(pixel(&image, 10 + 1, 10 + 8), theme.fg);
+
+        // not in any font, drawn as a box spanning both cells
+        assert_eq!(pixel(&image, 10 + 18 + 1, 10 + 10), theme.fg);
+        assert_eq!(pixel(&image, 10 + 18 + 16, 10 + 10), theme.fg);
+        assert_ne!(pixel(&image, 10 + 18 + 8, 10 + 10), theme.fg);
+    }
+
+    #[test]
+    fn clipped_glyph() {
+        let font = FontArc::try_from_vec(test_font()).unwrap();
+        let id = font.glyph_id('\u{4e2d}');
+        let coverage = rasterize(&font, id, (4.0, 14.0), 0.0, (9, 17));
+
+        // the part past the right edge doesn't show up on the left
+        assert!((0..17).all(|y| coverage[y * 9] == 0.0));
+        assert!((3..13).all(|y| coverage[y * 9 + 8] > 0.0));
+    }
+
+    #[test]
+    fn png() {
+        let theme = Theme::default();
+        let mut vt = avt::Vt::new(4, 1);
+        vt.feed_str("\x1b[41mA\x1b[0m");
+        let image = render(vt.view(), None, &theme);
+        let png = image.to_png();
+
+        let mut reader = ::png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (56, 37));
+        assert_eq!(info.color_type, ::png::ColorType::Rgb);
+        assert_eq!(pixels, image.pixels);
+    }
+
+    /// A TrueType font with a single glyph, a square, for U+4E2D.
+    fn test_font() -> Vec<u8> {
+        fn u16s(values: &[u16]) -> Vec<u8> {
+            values.iter().flat_map(|v| v.to_be_bytes()).collect()
+        }
+
+        let head = [
+            u16s(&[1, 0, 0, 0, 0, 0, 0x5f0f, 0x3cf5, 0, 1000]),
+            vec![0; 16],
+            u16s(&[100, 0, 900, 800, 0, 0, 2, 0, 0]),
+        ]
+        .concat();
+
+        let hhea = [
+            u16s(&[1, 0, 800, (-200i16) as u16, 0, 1000]),
+            vec![0; 22],
+            u16s(&[2]),
+        ]
+        .concat();
+        let maxp = u16s(&[0, 0x5000, 2]);
+        let hmtx = u16s(&[1000, 0, 1000, 0]);
+
+        // one contour of four points on the curve, as x and y deltas
+        let square = [
+            u16s(&[1, 100, 0, 900, 800, 3, 0]),
+            vec![1; 4],
+            u16s(&[100, 800, 0, (-800i16) as u16, 0, 0, 800, 0]),
+        ]
+        .concat();
+
+        let loca = u16s(&[0, 0, square.len() as u16 / 2]);
+
+        // format 4 subtable with a segment for U+4E2D and the final one
+        let cmap = u16s(&[
+            0,
+            1,
+            3,
+            1,
+            0,
+            12,
+            4,
+            32,
+            0,
+            4,
+            4,
+            1,
+            0,
+            0x4e2d,
+            0xffff,
+            0,
+            0x4e2d,
+            0xffff,
+            1u16.wrapping_sub(0x4e2d),
+            1,
+            0,
+            0,
+        ]);
+
+        let tables: [(&[u8; 4], Vec<u8>); 7] = [
+            (b"cmap", cmap),
+            (b"glyf", square),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ];
+
+        let mut font = u16s(&[1, 0, tables.len() as u16, 0, 0, 0]);
+        let mut offset = 12 + 16 * tables.len();
+        let mut data = Vec::new();
+
+        for (tag, table) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&[0; 4]);
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            data.extend_from_slice(table);
+            data.resize(data.len().next_multiple_of(4), 0);
+            offset = 12 + 16 * tables.len() + data.len();
+        }
+
+        font.extend(data);
+
+        font
+    }
+}
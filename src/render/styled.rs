@@ -0,0 +1,102 @@
+use serde::{Serialize, Serializer};
+
+pub type Line = Vec<Segment>;
+
+/// A run of text sharing the same pen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Segment {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<Color>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub faint: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub underline: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub strikethrough: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub blink: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub inverse: bool,
+}
+
+/// Indexed colors serialize as numbers (0-255), RGB colors as "#rrggbb".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub avt::Color);
+
+pub fn lines(view: &[avt::Line]) -> Vec<Line> {
+    view.iter().map(line).collect()
+}
+
+pub fn line(line: &avt::Line) -> Line {
+    line.chunks(|c1, c2| c1.pen() != c2.pen())
+        .map(|cells| segment(&cells))
+        .collect()
+}
+
+fn segment(cells: &[avt::Cell]) -> Segment {
+    let pen = cells[0].pen();
+
+    Segment {
+        text: cells.iter().map(|c| c.char()).collect(),
+        fg: pen.foreground().map(Color),
+        bg: pen.background().map(Color),
+        bold: pen.is_bold(),
+        faint: pen.is_faint(),
+        italic: pen.is_italic(),
+        underline: pen.is_underline(),
+        strikethrough: pen.is_strikethrough(),
+        blink: pen.is_blink(),
+        inverse: pen.is_inverse(),
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            avt::Color::Indexed(c) => serializer.serialize_u8(c),
+
+            avt::Color::RGB(c) => {
+                serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    #[test]
+    fn lines() {
+        let mut vt = avt::Vt::new(8, 2);
+        vt.feed_str("ab\x1b[1;31mcd\x1b[0;4;48;2;16;32;255mef\x1b[0m\r\n\x1b[7mgh");
+        let lines = super::lines(vt.view());
+
+        assert_eq!(
+            json!(lines),
+            json!([
+                [
+                    { "text": "ab" },
+                    { "text": "cd", "fg": 1, "bold": true },
+                    { "text": "ef", "bg": "#1020ff", "underline": true },
+                    { "text": "  " },
+                ],
+                [
+                    { "text": "gh", "inverse": true },
+                    { "text": "      " },
+                ]
+            ])
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use super::theme::{Style, Theme};
+use super::{escape, FONT_FAMILY};
+use std::fmt::Write;
+
+const FONT_SIZE: f64 = 14.0;
+const CELL_WIDTH: f64 = 8.4;
+const ROW_HEIGHT: f64 = 17.0;
+const BASELINE: f64 = 13.0;
+const PADDING: f64 = 10.0;
+
+/// A run of cells sharing the same style, starting at `col`.
+struct Run {
+    col: usize,
+    width: usize,
+    text: String,
+    style: Style,
+}
+
+/// Renders lines of the terminal as a standalone SVG image, with the cursor
+/// (col, row) drawn as a block.
+pub fn render(lines: &[avt::Line], cursor: Option<(usize, usize)>, theme: &Theme) -> String {
+    let cols = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+    let width = round(cols as f64 * CELL_WIDTH + 2.0 * PADDING);
+    let height = round(lines.len() as f64 * ROW_HEIGHT + 2.0 * PADDING);
+    let mut svg = String::new();
+
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="{FONT_FAMILY}" font-size="{FONT_SIZE}">"#
+    );
+
+    let _ = writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" rx="4" fill="{}"/>"#,
+        theme.bg
+    );
+
+    let rows: Vec<Vec<Run>> = lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let cursor_col = super::cursor_col(line, row, cursor);
+
+            runs(line, cursor_col, theme)
+        })
+        .collect();
+
+    svg.push_str("<g shape-rendering=\"crispEdges\">\n");
+
+    for (row, runs) in rows.iter().enumerate() {
+        for run in runs {
+            if let Some(bg) = run.style.bg {
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{ROW_HEIGHT}" fill="{bg}"/>"#,
+                    x(run.col),
+                    y(row),
+                    round(run.width as f64 * CELL_WIDTH),
+                );
+            }
+        }
+    }
+
+    svg.push_str("</g>\n");
+    svg.push_str("<text xml:space=\"preserve\">\n");
+
+    for (row, runs) in rows.iter().enumerate() {
+        let runs = runs
+            .iter()
+            .filter(|r| !r.text.trim().is_empty() || r.style.underline || r.style.strikethrough);
+
+        for run in runs {
+            let _ = writeln!(
+                svg,
+                r#"<tspan x="{}" y="{}" fill="{}"{}>{}</tspan>"#,
+                x(run.col),
+                round(y(row) + BASELINE),
+                run.style.fg,
+                attributes(&run.style),
+                escape(&run.text),
+            );
+        }
+    }
+
+    svg.push_str("</text>\n</svg>\n");
+
+    svg
+}
+
+fn runs(line: &avt::Line, cursor_col: Option<usize>, theme: &Theme) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+
+    for (col, cell) in line.cells().iter().enumerate() {
+        // the second half of a wide character
+        if cell.width() == 0 {
+            continue;
+        }
+
+        let style = theme.style(cell.pen(), cursor_col == Some(col));
+
+        match runs.last_mut() {
+            // wide characters get runs of their own, so that their actual
+            // width in the font doesn't shift the text following them
+            Some(run)
+                if run.style == style
+                    && cell.width() == 1
+                    && run.width == run.text.chars().count() =>
+            {
+                run.text.push(cell.char());
+                run.width += 1;
+            }
+
+            _ => runs.push(Run {
+                col,
+                width: cell.width(),
+                text: cell.char().to_string(),
+                style,
+            }),
+        }
+    }
+
+    runs
+}
+
+fn attributes(style: &Style) -> String {
+    let mut attrs = String::new();
+
+    if style.bold {
+        attrs.push_str(r#" font-weight="bold""#);
+    }
+
+    if style.faint {
+        attrs.push_str(r#" fill-opacity="0.5""#);
+    }
+
+    if style.italic {
+        attrs.push_str(r#" font-style="italic""#);
+    }
+
+    match (style.underline, style.strikethrough) {
+        (true, true) => attrs.push_str(r#" text-decoration="underline line-through""#),
+        (true, false) => attrs.push_str(r#" text-decoration="underline""#),
+        (false, true) => attrs.push_str(r#" text-decoration="line-through""#),
+        (false, false) => (),
+    }
+
+    attrs
+}
+
+fn x(col: usize) -> f64 {
+    round(PADDING + col as f64 * CELL_WIDTH)
+}
+
+fn y(row: usize) -> f64 {
+    round(PADDING + row as f64 * ROW_HEIGHT)
+}
+
+/// Rounds to 2 decimal places, keeping the output free of float noise.
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+    use crate::render::theme::Theme;
+
+    #[test]
+    fn styled_text() {
+        let mut vt = avt::Vt::new(10, 2);
+        vt.feed_str("a<b \x1b[1;31mred\x1b[0m\r\n\x1b[44mbg\x1b[0m");
+        let svg = render(vt.view(), None, &Theme::default());
+
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="104" height="54""#)
+        );
+        assert!(svg.contains(r##"<rect width="100%" height="100%" rx="4" fill="#121314"/>"##));
+        assert!(svg.contains(r##"<tspan x="10" y="23" fill="#cccccc">a&lt;b </tspan>"##));
+        assert!(svg
+            .contains(r##"<tspan x="43.6" y="23" fill="#dd3c69" font-weight="bold">red</tspan>"##));
+        assert!(svg.contains(r##"<rect x="10" y="27" width="16.8" height="17" fill="#26b0d7"/>"##));
+        assert!(svg.contains(r##"<tspan x="10" y="40" fill="#cccccc">bg</tspan>"##));
+        assert!(svg.ends_with("</text>\n</svg>\n"));
+    }
+
+    #[test]
+    fn cursor() {
+        let mut vt = avt::Vt::new(4, 1);
+        vt.feed_str("ab");
+        let svg = render(vt.view(), Some((1, 0)), &Theme::default());
+
+        assert!(svg.contains(r##"<rect x="18.4" y="10" width="8.4" height="17" fill="#cccccc"/>"##));
+        assert!(svg.contains(r##"<tspan x="18.4" y="23" fill="#121314">b</tspan>"##));
+    }
+
+    #[test]
+    fn wide_chars() {
+        let mut vt = avt::Vt::new(6, 1);
+        vt.feed_str("a\u{4e2d}b");
+        let svg = render(vt.view(), None, &Theme::default());
+
+        assert!(svg.contains(r##"<tspan x="10" y="23" fill="#cccccc">a</tspan>"##));
+        assert!(svg.contains(r##"<tspan x="18.4" y="23" fill="#cccccc">中</tspan>"##));
+        assert!(svg.contains(r##"<tspan x="35.2" y="23" fill="#cccccc">b  </tspan>"##));
+    }
+}
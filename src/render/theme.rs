@@ -0,0 +1,216 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Colors used when rendering the terminal to an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub fg: Rgb,
+    pub bg: Rgb,
+    pub palette: [Rgb; 16],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// Resolved look of a cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub fg: Rgb,
+    pub bg: Option<Rgb>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+const BUILT_IN: [(&str, &str); 5] = [
+    (
+        "asciinema",
+        "cccccc,121314,000000,dd3c69,4ebf22,ddaf3c,26b0d7,b954e1,54e1b9,d9d9d9,\
+         4d4d4d,dd3c69,4ebf22,ddaf3c,26b0d7,b954e1,54e1b9,ffffff",
+    ),
+    (
+        "dracula",
+        "f8f8f2,282a36,21222c,ff5555,50fa7b,f1fa8c,bd93f9,ff79c6,8be9fd,f8f8f2,\
+         6272a4,ff6e6e,69ff94,ffffa5,d6acff,ff92df,a4ffff,ffffff",
+    ),
+    (
+        "monokai",
+        "f8f8f2,272822,272822,f92672,a6e22e,f4bf75,66d9ef,ae81ff,a1efe4,f8f8f2,\
+         75715e,f92672,a6e22e,f4bf75,66d9ef,ae81ff,a1efe4,f9f8f5",
+    ),
+    (
+        "solarized-dark",
+        "839496,002b36,073642,dc322f,859900,b58900,268bd2,d33682,2aa198,eee8d5,\
+         002b36,cb4b16,586e75,657b83,839496,6c71c4,93a1a1,fdf6e3",
+    ),
+    (
+        "solarized-light",
+        "657b83,fdf6e3,073642,dc322f,859900,b58900,268bd2,d33682,2aa198,eee8d5,\
+         002b36,cb4b16,586e75,657b83,839496,6c71c4,93a1a1,fdf6e3",
+    ),
+];
+
+impl Theme {
+    /// Resolves a cell color to RGB. Indexed colors 16-255 follow the
+    /// standard xterm 6x6x6 color cube and grayscale ramp.
+    pub fn color(&self, color: avt::Color) -> Rgb {
+        match color {
+            avt::Color::RGB(c) => Rgb(c.r, c.g, c.b),
+            avt::Color::Indexed(i) if i < 16 => self.palette[i as usize],
+
+            avt::Color::Indexed(i) if i < 232 => {
+                let i = i - 16;
+                let level = |v: u8| if v == 0 { 0 } else { v * 40 + 55 };
+
+                Rgb(level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+
+            avt::Color::Indexed(i) => {
+                let v = (i - 232) * 10 + 8;
+
+                Rgb(v, v, v)
+            }
+        }
+    }
+
+    /// Resolves the look of a cell with the given pen, drawn inverted when
+    /// the cursor is over it. The background is `None` when it's the
+    /// theme's default.
+    pub fn style(&self, pen: &avt::Pen, cursor: bool) -> Style {
+        // bold text in one of the 8 basic colors is shown in its bright variant
+        let fg = match pen.foreground() {
+            Some(avt::Color::Indexed(i)) if i < 8 && pen.is_bold() => {
+                Some(self.color(avt::Color::Indexed(i + 8)))
+            }
+
+            fg => fg.map(|c| self.color(c)),
+        };
+
+        let bg = pen.background().map(|c| self.color(c));
+
+        let (fg, bg) = if pen.is_inverse() != cursor {
+            (bg.unwrap_or(self.bg), Some(fg.unwrap_or(self.fg)))
+        } else {
+            (fg.unwrap_or(self.fg), bg)
+        };
+
+        Style {
+            fg,
+            bg,
+            bold: pen.is_bold(),
+            faint: pen.is_faint(),
+            italic: pen.is_italic(),
+            underline: pen.is_underline(),
+            strikethrough: pen.is_strikethrough(),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        BUILT_IN[0].1.parse().unwrap()
+    }
+}
+
+/// Parses either a built-in theme name, or a comma-separated list of hex
+/// colors: foreground, background and 8 or 16 palette colors.
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, colors)) = BUILT_IN.iter().find(|(name, _)| *name == s) {
+            return colors.parse();
+        }
+
+        let colors = s
+            .split(',')
+            .map(|c| c.trim().parse())
+            .collect::<Result<Vec<Rgb>, _>>()?;
+
+        let palette: [Rgb; 16] = match colors.len() {
+            10 => std::array::from_fn(|i| colors[2 + i % 8]),
+            18 => std::array::from_fn(|i| colors[2 + i]),
+
+            _ => {
+                let names: Vec<_> = BUILT_IN.iter().map(|(name, _)| *name).collect();
+
+                return Err(format!(
+                    "invalid theme: {s} (expected one of {} or a list of 10 or 18 colors)",
+                    names.join(", ")
+                ));
+            }
+        };
+
+        Ok(Theme {
+            fg: colors[0],
+            bg: colors[1],
+            palette,
+        })
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(format!("invalid color: {s}"));
+        }
+
+        let component = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("invalid color: {s}"))
+        };
+
+        Ok(Rgb(component(0)?, component(2)?, component(4)?))
+    }
+}
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Rgb, Theme};
+
+    #[test]
+    fn parse() {
+        let theme: Theme = "dracula".parse().unwrap();
+
+        assert_eq!(theme.fg, Rgb(0xf8, 0xf8, 0xf2));
+        assert_eq!(theme.palette[9], Rgb(0xff, 0x6e, 0x6e));
+
+        let theme: Theme = "#eeeeee,000000,000000,aa0000,00aa00,aaaa00,0000aa,aa00aa,00aaaa,aaaaaa"
+            .parse()
+            .unwrap();
+
+        assert_eq!(theme.fg, Rgb(0xee, 0xee, 0xee));
+        assert_eq!(theme.palette[1], Rgb(0xaa, 0, 0));
+        assert_eq!(theme.palette[9], Rgb(0xaa, 0, 0));
+
+        assert!("nope".parse::<Theme>().is_err());
+        assert!("eeeeee,000000".parse::<Theme>().is_err());
+        assert!(
+            "eeeeee,00000g,000000,aa0000,00aa00,aaaa00,0000aa,aa00aa,00aaaa,aaaaaa"
+                .parse::<Theme>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn color() {
+        let theme = Theme::default();
+
+        assert_eq!(theme.color(avt::Color::Indexed(1)), theme.palette[1]);
+        assert_eq!(theme.color(avt::Color::Indexed(16)), Rgb(0, 0, 0));
+        assert_eq!(theme.color(avt::Color::Indexed(196)), Rgb(255, 0, 0));
+        assert_eq!(theme.color(avt::Color::Indexed(244)), Rgb(128, 128, 128));
+        assert_eq!(theme.color(avt::Color::rgb(1, 2, 3)), Rgb(1, 2, 3));
+    }
+}
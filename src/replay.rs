@@ -0,0 +1,268 @@
+use crate::asciicast::{Event, Recording};
+use crate::pty::{ExitStatus, Input, Output};
+use anyhow::Result;
+use std::fmt::Display;
+use std::future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Bounds of the playback speed factor, beyond which the playback would be
+/// practically stopped or instant.
+const MIN_SPEED: f64 = 0.001;
+const MAX_SPEED: f64 = 1000.0;
+
+/// Playback speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    Factor(f64),
+    Instant,
+}
+
+/// Plays back the recording's output and resize events, in place of a
+/// process running in a PTY. With `keep_alive` the playback doesn't finish
+/// at the end of the recording, so it's still possible to seek.
+pub async fn drive(
+    recording: Arc<Recording>,
+    speed: Speed,
+    keep_alive: bool,
+    mut input_rx: mpsc::Receiver<Input>,
+    output_tx: mpsc::Sender<Output>,
+    mut seek_rx: mpsc::Receiver<f64>,
+) -> Result<ExitStatus> {
+    let events = &recording.events;
+    // start from a clean terminal of the recording's size, e.g. on restart
+    let Ok(mut position) = seek(&recording, 0.0, &output_tx).await else {
+        return Ok(ExitStatus::Exited(0));
+    };
+    let mut clock = Clock::new(0.0, speed);
+
+    loop {
+        let next = events.get(position);
+
+        if next.is_none() && !keep_alive {
+            return Ok(ExitStatus::Exited(0));
+        }
+
+        let deadline = next.and_then(|e| clock.deadline(e.time()));
+
+        tokio::select! {
+            _ = sleep_until(deadline) => {
+                // nobody reads the output anymore, e.g. when stopping
+                if send(&output_tx, &events[position]).await.is_err() {
+                    return Ok(ExitStatus::Exited(0));
+                }
+
+                position += 1;
+            }
+
+            input = input_rx.recv() => {
+                match input {
+                    // there's no process to send input or signals to
+                    Some(_) => (),
+
+                    None => {
+                        return Ok(ExitStatus::Exited(0));
+                    }
+                }
+            }
+
+            Some(time) = seek_rx.recv() => {
+                let Ok(new_position) = seek(&recording, time, &output_tx).await else {
+                    return Ok(ExitStatus::Exited(0));
+                };
+
+                position = new_position;
+                clock = Clock::new(time, speed);
+            }
+        }
+    }
+}
+
+/// Resets the terminal and fast-forwards it to the state at `time`, returning
+/// position of the first event after it.
+async fn seek(recording: &Recording, time: f64, output_tx: &mpsc::Sender<Output>) -> Result<usize> {
+    let header = &recording.header;
+    let mut data = String::from("\x1bc");
+    let mut position = 0;
+
+    output_tx
+        .send(Output::Resize(header.cols, header.rows))
+        .await?;
+
+    for event in recording.events.iter().take_while(|e| e.time() <= time) {
+        match event {
+            Event::Output(_, output) => {
+                data.push_str(output);
+            }
+
+            Event::Resize(_, cols, rows) => {
+                let data = std::mem::take(&mut data).into_bytes();
+                output_tx.send(Output::Data(data)).await?;
+                output_tx.send(Output::Resize(*cols, *rows)).await?;
+            }
+
+            Event::Input(_, _) => (),
+        }
+
+        position += 1;
+    }
+
+    if !data.is_empty() {
+        output_tx.send(Output::Data(data.into_bytes())).await?;
+    }
+
+    Ok(position)
+}
+
+async fn send(output_tx: &mpsc::Sender<Output>, event: &Event) -> Result<()> {
+    match event {
+        Event::Output(_, data) => {
+            output_tx
+                .send(Output::Data(data.as_bytes().to_vec()))
+                .await?;
+        }
+
+        Event::Resize(_, cols, rows) => {
+            output_tx.send(Output::Resize(*cols, *rows)).await?;
+        }
+
+        Event::Input(_, _) => (),
+    }
+
+    Ok(())
+}
+
+/// Maps recording time to wall clock time, starting at recording time
+/// `offset` now.
+struct Clock {
+    start: Instant,
+    offset: f64,
+    speed: Speed,
+}
+
+impl Clock {
+    fn new(offset: f64, speed: Speed) -> Self {
+        Self {
+            start: Instant::now(),
+            offset,
+            speed,
+        }
+    }
+
+    /// Wall clock time of the recording time, or `None` if it's too far in
+    /// the future to ever come.
+    fn deadline(&self, time: f64) -> Option<Instant> {
+        match self.speed {
+            Speed::Factor(factor) => {
+                let delay = ((time - self.offset) / factor).max(0.0);
+
+                Duration::try_from_secs_f64(delay)
+                    .ok()
+                    .and_then(|delay| self.start.checked_add(delay))
+            }
+
+            Speed::Instant => Some(self.start),
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => future::pending().await,
+    }
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "instant" => Ok(Speed::Instant),
+
+            s => match s.parse::<f64>() {
+                Ok(factor) if (MIN_SPEED..=MAX_SPEED).contains(&factor) => {
+                    Ok(Speed::Factor(factor))
+                }
+
+                _ => Err(format!(
+                    "invalid speed: {s}, expected \"instant\" or {MIN_SPEED} to {MAX_SPEED}"
+                )),
+            },
+        }
+    }
+}
+
+impl Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Speed::Factor(factor) => write!(f, "{factor}x"),
+            Speed::Instant => write!(f, "instant"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{seek, Clock, Speed};
+    use crate::asciicast::Recording;
+    use crate::pty::Output;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn parse_speed() {
+        assert_eq!("2".parse(), Ok(Speed::Factor(2.0)));
+        assert_eq!("0.5".parse(), Ok(Speed::Factor(0.5)));
+        assert_eq!("instant".parse(), Ok(Speed::Instant));
+        assert!("0".parse::<Speed>().is_err());
+        assert!("fast".parse::<Speed>().is_err());
+        assert!("1e-300".parse::<Speed>().is_err());
+        assert!("1e300".parse::<Speed>().is_err());
+        assert!("inf".parse::<Speed>().is_err());
+        assert!("NaN".parse::<Speed>().is_err());
+    }
+
+    #[test]
+    fn deadline() {
+        let clock = Clock::new(10.0, Speed::Factor(2.0));
+
+        assert_eq!(
+            clock.deadline(12.0),
+            Some(clock.start + Duration::from_secs(1))
+        );
+        assert_eq!(clock.deadline(5.0), Some(clock.start));
+        assert_eq!(clock.deadline(1e300), None);
+        assert!(Clock::new(0.0, Speed::Instant).deadline(1e300).is_some());
+    }
+
+    #[tokio::test]
+    async fn seek_to_time() {
+        let recording = Recording::parse(concat!(
+            r#"{"version":2,"width":80,"height":24}"#,
+            "\n",
+            r#"[1.0,"o","a"]"#,
+            "\n",
+            r#"[2.0,"o","b"]"#,
+            "\n",
+            r#"[3.0,"r","100x30"]"#,
+            "\n",
+            r#"[4.0,"o","c"]"#,
+        ))
+        .unwrap();
+
+        let (output_tx, mut output_rx) = mpsc::channel(10);
+        let position = seek(&recording, 2.5, &output_tx).await.unwrap();
+        drop(output_tx);
+
+        assert_eq!(position, 2);
+        assert!(matches!(
+            output_rx.recv().await,
+            Some(Output::Resize(80, 24))
+        ));
+        assert!(matches!(output_rx.recv().await, Some(Output::Data(data)) if data == b"\x1bcab"));
+        assert!(output_rx.recv().await.is_none());
+    }
+}
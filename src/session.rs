@@ -1,77 +1,321 @@
+use crate::command::{ClientId, Id, Reply};
+use crate::pty::ExitStatus;
+use crate::render::{self, theme::Theme, Rendering};
+use crate::tracker::{Modes, Report, Tracker};
+use crate::wait::{self, Wait};
 use anyhow::Result;
+use base64::prelude::{Engine, BASE64_STANDARD};
 use futures_util::{stream, Stream, StreamExt};
 use serde_json::json;
+use std::collections::BTreeSet;
 use std::future;
 use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
 
+/// How long changed lines are collected before being sent as a `view` event.
+const VIEW_DEBOUNCE: Duration = Duration::from_millis(30);
+
 pub struct Session {
     vt: avt::Vt,
+    tracker: Tracker,
     broadcast_tx: broadcast::Sender<Event>,
+    sinks: Vec<mpsc::UnboundedSender<Event>>,
     stream_time: f64,
     start_time: Instant,
     last_event_time: Instant,
+    last_output_time: Instant,
+    idle_time: Duration,
+    scrollback: usize,
+    theme: Theme,
+    active: bool,
     pid: i32,
+    waits: Vec<(Reply, Wait)>,
+    dirty_lines: BTreeSet<usize>,
+    view_deadline: Option<Instant>,
+    driver: Option<ClientId>,
 }
 
 #[derive(Clone)]
 pub enum Event {
-    Init(f64, usize, usize, i32, String, String),
+    Init(
+        f64,
+        usize,
+        usize,
+        i32,
+        String,
+        String,
+        Cursor,
+        Modes,
+        Option<ClientId>,
+    ),
     Output(f64, String),
+    Input(f64, String),
     Resize(f64, usize, usize),
-    Snapshot(usize, usize, String, String),
+    Snapshot(
+        Option<Reply>,
+        usize,
+        usize,
+        String,
+        String,
+        Cursor,
+        Modes,
+        Option<Rendering>,
+    ),
+    WaitResult(Reply, wait::Outcome),
+    Idle,
+    Ack(Reply),
+    Error(Reply, String),
+    Scrollback(Reply, usize, usize, String, Option<Rendering>),
+    View(usize, usize, Vec<(usize, String)>),
+    Exit(ExitStatus),
+    Title(String),
+    Bell,
+    Notification(Option<String>, String),
+    Screenshot(Reply, usize, usize, String),
+    Control(Option<ClientId>),
 }
 
-pub struct Client(oneshot::Sender<Subscription>);
+#[derive(Clone)]
+pub struct Cursor {
+    pub col: usize,
+    pub row: usize,
+    pub visible: bool,
+}
+
+pub enum Client {
+    /// Subscribes to the session's events.
+    Subscribe(oneshot::Sender<Subscription>),
+    /// Renders the current terminal view as an image.
+    Render(render::Image, oneshot::Sender<Vec<u8>>),
+}
 
 pub struct Subscription {
     init: Event,
+    view: Event,
     broadcast_rx: broadcast::Receiver<Event>,
 }
 
 impl Session {
-    pub fn new(cols: usize, rows: usize, pid: i32) -> Self {
+    pub fn new(
+        cols: usize,
+        rows: usize,
+        pid: i32,
+        idle_time: Duration,
+        scrollback: usize,
+        theme: Theme,
+    ) -> Self {
         let (broadcast_tx, _) = broadcast::channel(1024);
         let now = Instant::now();
 
         Self {
-            vt: build_vt(cols, rows),
+            vt: build_vt(cols, rows, scrollback),
+            tracker: Tracker::default(),
             broadcast_tx,
+            sinks: Vec::new(),
             stream_time: 0.0,
             start_time: now,
             last_event_time: now,
+            last_output_time: now,
+            idle_time,
+            scrollback,
+            theme,
+            active: false,
             pid,
+            waits: Vec::new(),
+            dirty_lines: BTreeSet::new(),
+            view_deadline: None,
+            driver: None,
         }
     }
 
     pub fn output(&mut self, data: String) {
-        self.vt.feed_str(&data);
+        let lines = self.vt.feed_str(&data).lines;
+        self.mark_dirty(lines);
+        let reports = self.tracker.feed_str(&data);
         let time = self.start_time.elapsed().as_secs_f64();
-        let _ = self.broadcast_tx.send(Event::Output(time, data));
+        self.send_stream_event(Event::Output(time, data));
+
+        for report in reports {
+            let event = match report {
+                Report::Title(title) => Event::Title(title),
+                Report::Bell => Event::Bell,
+                Report::Notification(title, body) => Event::Notification(title, body),
+            };
+
+            let _ = self.broadcast_tx.send(event);
+        }
+
         self.stream_time = time;
         self.last_event_time = Instant::now();
+        self.last_output_time = self.last_event_time;
+        self.active = true;
+        self.check_waits();
+    }
+
+    pub fn input(&self, data: &[u8]) {
+        let time = self.start_time.elapsed().as_secs_f64();
+        let data = String::from_utf8_lossy(data).to_string();
+        self.send_stream_event(Event::Input(time, data));
     }
 
     pub fn resize(&mut self, cols: usize, rows: usize) {
-        resize_vt(&mut self.vt, cols, rows);
+        let lines = resize_vt(&mut self.vt, cols, rows);
+        self.mark_dirty(lines);
         let time = self.start_time.elapsed().as_secs_f64();
-        let _ = self.broadcast_tx.send(Event::Resize(time, cols, rows));
+        self.send_stream_event(Event::Resize(time, cols, rows));
         self.stream_time = time;
         self.last_event_time = Instant::now();
+        self.check_waits();
     }
 
-    pub fn snapshot(&self) {
+    pub fn snapshot(&self, reply: Option<Reply>, format: render::Format) {
         let (cols, rows) = self.vt.size();
 
         let _ = self.broadcast_tx.send(Event::Snapshot(
+            reply,
             cols,
             rows,
             self.vt.dump(),
             self.text_view(),
+            self.cursor(),
+            self.modes(),
+            render::render(&self.vt, format, &self.theme),
         ));
     }
 
+    pub fn screenshot(&self, reply: Reply) {
+        let (cols, rows) = self.vt.size();
+        let broadcast_tx = self.broadcast_tx.clone();
+
+        self.png(move |png| {
+            let _ = broadcast_tx.send(Event::Screenshot(
+                reply,
+                cols,
+                rows,
+                BASE64_STANDARD.encode(png),
+            ));
+        });
+    }
+
+    pub fn get_scrollback(
+        &self,
+        reply: Reply,
+        offset: usize,
+        limit: Option<usize>,
+        format: render::Format,
+    ) {
+        let scrollback = self.scrollback_lines();
+        let total = scrollback.len();
+        let start = offset.min(total);
+        let end = limit.map_or(total, |l| (start + l).min(total));
+        let lines = &scrollback[start..end];
+
+        let _ = self.broadcast_tx.send(Event::Scrollback(
+            reply,
+            total,
+            start,
+            text(lines),
+            render::render_lines(lines, None, format, &self.theme),
+        ));
+    }
+
+    /// Reports exit of the child process, preceded by a snapshot of the
+    /// terminal's final state.
+    pub fn exit(&mut self, status: ExitStatus) {
+        self.flush_view();
+        self.snapshot(None, render::Format::Text);
+        let _ = self.broadcast_tx.send(Event::Exit(status));
+    }
+
+    pub fn wait_for(&mut self, reply: Reply, wait: Wait) {
+        self.waits.push((reply, wait));
+        self.check_waits();
+    }
+
+    /// Starts over with a new process, resetting the terminal.
+    pub fn restart(&mut self, pid: i32) {
+        self.pid = pid;
+        self.output("\x1bc".to_owned());
+    }
+
+    /// Gives the client exclusive control of the terminal, unless another one
+    /// already has it.
+    pub fn take_control(&mut self, reply: Reply) {
+        if self.is_controlled_by_other(reply.client) {
+            self.error(reply, "another client has taken control of the terminal");
+
+            return;
+        }
+
+        if self.driver.is_none() {
+            self.driver = Some(reply.client);
+            let _ = self.broadcast_tx.send(Event::Control(self.driver));
+        }
+
+        self.ack(reply);
+    }
+
+    pub fn release_control(&mut self, reply: Reply) {
+        if self.driver == Some(reply.client) {
+            self.driver = None;
+            let _ = self.broadcast_tx.send(Event::Control(None));
+        }
+
+        self.ack(reply);
+    }
+
+    /// Tells whether another client has taken control of the terminal, in
+    /// which case the client's input is rejected.
+    pub fn is_controlled_by_other(&self, client: ClientId) -> bool {
+        self.driver.is_some_and(|driver| driver != client)
+    }
+
+    /// Renders the current view as a standalone SVG image.
+    pub fn svg(&self) -> String {
+        render::svg(&self.vt, &self.theme)
+    }
+
+    pub fn error<S: ToString>(&self, reply: Reply, message: S) {
+        let _ = self
+            .broadcast_tx
+            .send(Event::Error(reply, message.to_string()));
+    }
+
+    pub fn ack(&self, reply: Reply) {
+        if reply.id.is_some() {
+            let _ = self.broadcast_tx.send(Event::Ack(reply));
+        }
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let idle_deadline = Some(self.last_output_time + self.idle_time).filter(|_| self.active);
+
+        self.waits
+            .iter()
+            .filter_map(|(_, w)| w.next_deadline(self.last_output_time))
+            .chain(idle_deadline)
+            .chain(self.view_deadline)
+            .min()
+    }
+
+    pub fn tick(&mut self) {
+        self.check_waits();
+
+        if self.view_deadline.is_some_and(|d| d <= Instant::now()) {
+            self.flush_view();
+        }
+
+        if self.active && self.last_output_time.elapsed() >= self.idle_time {
+            self.active = false;
+            let _ = self.broadcast_tx.send(Event::Idle);
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        self.vt.size()
+    }
+
     pub fn cursor_key_app_mode(&self) -> bool {
         self.vt.cursor_key_app_mode()
     }
@@ -86,31 +330,169 @@ impl Session {
             self.pid,
             self.vt.dump(),
             self.text_view(),
+            self.cursor(),
+            self.modes(),
+            self.driver,
         );
 
+        let view = Event::View(cols, rows, self.view_lines(0..rows));
         let broadcast_rx = self.broadcast_tx.subscribe();
 
-        Subscription { init, broadcast_rx }
+        Subscription {
+            init,
+            view,
+            broadcast_rx,
+        }
+    }
+
+    pub fn serve(&self, client: Client) {
+        match client {
+            Client::Subscribe(sub_tx) => {
+                let _ = sub_tx.send(self.subscribe());
+            }
+
+            Client::Render(render::Image::Png, image_tx) => {
+                self.png(move |png| {
+                    let _ = image_tx.send(png);
+                });
+            }
+
+            Client::Render(image, image_tx) => {
+                let _ = image_tx.send(render::image(&self.vt, image, &self.theme));
+            }
+        }
+    }
+
+    /// Streams init event, followed by all output, input and resize events.
+    /// Unlike broadcast subscribers, which skip events when lagging behind,
+    /// the sink gets every one of them, e.g. for recording.
+    pub fn sink(&mut self) -> mpsc::UnboundedReceiver<Event> {
+        let (sink_tx, sink_rx) = mpsc::unbounded_channel();
+        let _ = sink_tx.send(self.subscribe().init);
+        self.sinks.push(sink_tx);
+
+        sink_rx
+    }
+
+    /// Renders the current view as a PNG image on a blocking thread, not to
+    /// hold up the event loop, and hands it over to `done`.
+    fn png(&self, done: impl FnOnce(Vec<u8>) + Send + 'static) {
+        let lines = self.vt.view().to_vec();
+        let cursor = render::cursor(&self.vt);
+        let theme = self.theme.clone();
+
+        tokio::task::spawn_blocking(move || done(render::png(&lines, cursor, &theme)));
+    }
+
+    fn send_stream_event(&self, event: Event) {
+        for sink in &self.sinks {
+            let _ = sink.send(event.clone());
+        }
+
+        let _ = self.broadcast_tx.send(event);
+    }
+
+    fn mark_dirty(&mut self, lines: Vec<usize>) {
+        if lines.is_empty() {
+            return;
+        }
+
+        self.dirty_lines.extend(lines);
+        self.view_deadline
+            .get_or_insert_with(|| Instant::now() + VIEW_DEBOUNCE);
+    }
+
+    fn flush_view(&mut self) {
+        let (cols, rows) = self.vt.size();
+        let dirty_lines = std::mem::take(&mut self.dirty_lines);
+        let lines = self.view_lines(dirty_lines.into_iter().filter(|&row| row < rows));
+        self.view_deadline = None;
+
+        if !lines.is_empty() {
+            let _ = self.broadcast_tx.send(Event::View(cols, rows, lines));
+        }
+    }
+
+    fn view_lines(&self, rows: impl Iterator<Item = usize>) -> Vec<(usize, String)> {
+        let view = self.vt.view();
+
+        rows.map(|row| (row, view[row].text())).collect()
+    }
+
+    fn cursor(&self) -> Cursor {
+        let (cols, _) = self.vt.size();
+        let cursor = self.vt.cursor();
+
+        Cursor {
+            col: cursor.col.min(cols - 1),
+            row: cursor.row,
+            visible: cursor.visible,
+        }
+    }
+
+    pub fn modes(&self) -> Modes {
+        Modes {
+            cursor_key_app: self.vt.cursor_key_app_mode(),
+            ..self.tracker.modes()
+        }
     }
 
     fn elapsed_time(&self) -> f64 {
         self.stream_time + self.last_event_time.elapsed().as_secs_f64()
     }
 
+    fn check_waits(&mut self) {
+        let now = Instant::now();
+        let vt = &self.vt;
+        let last_output_time = self.last_output_time;
+        let broadcast_tx = &self.broadcast_tx;
+
+        self.waits.retain(
+            |(reply, wait)| match wait.check(vt, last_output_time, now) {
+                Some(outcome) => {
+                    let _ = broadcast_tx.send(Event::WaitResult(reply.clone(), outcome));
+
+                    false
+                }
+
+                None => true,
+            },
+        );
+    }
+
     fn text_view(&self) -> String {
-        self.vt
-            .view()
-            .iter()
-            .map(|l| l.text())
-            .collect::<Vec<_>>()
-            .join("\n")
+        text(self.vt.view())
+    }
+
+    /// Lines above the visible screen, oldest first. avt trims its buffer
+    /// lazily, so it may hold slightly more than the configured limit.
+    fn scrollback_lines(&self) -> &[avt::Line] {
+        let lines = self.vt.lines();
+        let (_, rows) = self.vt.size();
+        let end = lines.len() - rows;
+        let start = end.saturating_sub(self.scrollback);
+
+        &lines[start..end]
     }
 }
 
 impl Event {
+    /// The client which the event replies to. Other events are for everyone.
+    pub fn recipient(&self) -> Option<ClientId> {
+        match self {
+            Event::Snapshot(reply, _, _, _, _, _, _, _) => reply.as_ref().map(|r| r.client),
+            Event::WaitResult(reply, _) => Some(reply.client),
+            Event::Ack(reply) => Some(reply.client),
+            Event::Error(reply, _) => Some(reply.client),
+            Event::Scrollback(reply, _, _, _, _) => Some(reply.client),
+            Event::Screenshot(reply, _, _, _) => Some(reply.client),
+            _ => None,
+        }
+    }
+
     pub fn to_json(&self) -> serde_json::Value {
         match self {
-            Event::Init(_time, cols, rows, pid, seq, text) => json!({
+            Event::Init(_time, cols, rows, pid, seq, text, cursor, modes, driver) => json!({
                 "type": "init",
                 "data": json!({
                     "cols": cols,
@@ -118,6 +500,9 @@ impl Event {
                     "pid": pid,
                     "seq": seq,
                     "text": text,
+                    "cursor": cursor_json(cursor),
+                    "modes": modes_json(modes),
+                    "driver": driver,
                 })
             }),
 
@@ -128,6 +513,13 @@ impl Event {
                 })
             }),
 
+            Event::Input(_time, seq) => json!({
+                "type": "input",
+                "data": json!({
+                    "seq": seq
+                })
+            }),
+
             Event::Resize(_time, cols, rows) => json!({
                 "type": "resize",
                 "data": json!({
@@ -136,41 +528,382 @@ impl Event {
                 })
             }),
 
-            Event::Snapshot(cols, rows, seq, text) => json!({
-                "type": "snapshot",
-                "data": json!({
+            Event::Snapshot(reply, cols, rows, seq, text, cursor, modes, rendering) => {
+                let mut data = json!({
                     "cols": cols,
                     "rows": rows,
                     "seq": seq,
                     "text": text,
+                    "cursor": cursor_json(cursor),
+                    "modes": modes_json(modes),
+                });
+
+                if let Some(rendering) = rendering {
+                    let (key, value) = rendering.to_json();
+                    data[key] = value;
+                }
+
+                with_id(
+                    json!({
+                        "type": "snapshot",
+                        "data": data
+                    }),
+                    reply.as_ref().and_then(|r| r.id.as_ref()),
+                )
+            }
+
+            Event::WaitResult(reply, wait::Outcome::Matched(m)) => with_id(
+                json!({
+                    "type": "waitResult",
+                    "data": json!({
+                        "status": "matched",
+                        "col": m.col,
+                        "row": m.row,
+                        "text": m.text,
+                    })
+                }),
+                reply.id.as_ref(),
+            ),
+
+            Event::WaitResult(reply, wait::Outcome::Idle) => with_id(
+                json!({
+                    "type": "waitResult",
+                    "data": json!({
+                        "status": "idle",
+                    })
+                }),
+                reply.id.as_ref(),
+            ),
+
+            Event::WaitResult(reply, wait::Outcome::Timeout) => with_id(
+                json!({
+                    "type": "waitResult",
+                    "data": json!({
+                        "status": "timeout",
+                    })
+                }),
+                reply.id.as_ref(),
+            ),
+
+            Event::Idle => json!({
+                "type": "idle",
+                "data": json!({})
+            }),
+
+            Event::Ack(reply) => with_id(
+                json!({
+                    "type": "ack",
+                    "data": json!({})
+                }),
+                reply.id.as_ref(),
+            ),
+
+            Event::Error(reply, message) => with_id(
+                json!({
+                    "type": "error",
+                    "data": json!({
+                        "message": message,
+                    })
+                }),
+                reply.id.as_ref(),
+            ),
+
+            Event::Scrollback(reply, total, offset, text, rendering) => {
+                let mut data = json!({
+                    "total": total,
+                    "offset": offset,
+                    "text": text,
+                });
+
+                if let Some(rendering) = rendering {
+                    let (key, value) = rendering.to_json();
+                    data[key] = value;
+                }
+
+                with_id(
+                    json!({
+                        "type": "scrollback",
+                        "data": data
+                    }),
+                    reply.id.as_ref(),
+                )
+            }
+
+            Event::View(cols, rows, lines) => json!({
+                "type": "view",
+                "data": json!({
+                    "cols": cols,
+                    "rows": rows,
+                    "changes": view_lines_json(lines),
+                })
+            }),
+
+            Event::Exit(status) => json!({
+                "type": "exit",
+                "data": json!({
+                    "code": status.code(),
+                    "signal": match status {
+                        ExitStatus::Exited(_) => None,
+                        ExitStatus::Signaled(signal) => Some(signal.as_str()),
+                    },
+                })
+            }),
+
+            Event::Title(title) => json!({
+                "type": "title",
+                "data": json!({
+                    "title": title,
+                })
+            }),
+
+            Event::Bell => json!({
+                "type": "bell",
+                "data": json!({})
+            }),
+
+            Event::Notification(title, body) => json!({
+                "type": "notification",
+                "data": json!({
+                    "title": title,
+                    "body": body,
+                })
+            }),
+
+            Event::Screenshot(reply, cols, rows, png) => with_id(
+                json!({
+                    "type": "screenshot",
+                    "data": json!({
+                        "cols": cols,
+                        "rows": rows,
+                        "png": png,
+                    })
+                }),
+                reply.id.as_ref(),
+            ),
+
+            Event::Control(driver) => json!({
+                "type": "control",
+                "data": json!({
+                    "driver": driver,
                 })
             }),
         }
     }
 }
 
-fn build_vt(cols: usize, rows: usize) -> avt::Vt {
-    avt::Vt::builder().size(cols, rows).build()
+fn view_lines_json(lines: &[(usize, String)]) -> serde_json::Value {
+    lines
+        .iter()
+        .map(|(row, text)| json!({ "row": row, "text": text }))
+        .collect()
 }
 
-fn resize_vt(vt: &mut avt::Vt, cols: usize, rows: usize) {
-    vt.resize(cols, rows);
+fn text(lines: &[avt::Line]) -> String {
+    lines
+        .iter()
+        .map(|l| l.text())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-impl Client {
-    pub fn accept(self, subscription: Subscription) {
-        let _ = self.0.send(subscription);
+fn cursor_json(cursor: &Cursor) -> serde_json::Value {
+    json!({
+        "col": cursor.col,
+        "row": cursor.row,
+        "visible": cursor.visible,
+    })
+}
+
+fn modes_json(modes: &Modes) -> serde_json::Value {
+    json!({
+        "alternateScreen": modes.alternate_screen,
+        "bracketedPaste": modes.bracketed_paste,
+        "mouseTracking": modes.mouse_tracking.as_str(),
+        "mouseEncoding": modes.mouse_encoding.as_str(),
+        "cursorKeyAppMode": modes.cursor_key_app,
+        "keypadAppMode": modes.keypad_app,
+    })
+}
+
+fn with_id(mut value: serde_json::Value, id: Option<&Id>) -> serde_json::Value {
+    if let Some(id) = id {
+        value["id"] = id.clone();
     }
+
+    value
+}
+
+fn build_vt(cols: usize, rows: usize, scrollback: usize) -> avt::Vt {
+    avt::Vt::builder()
+        .size(cols, rows)
+        .scrollback_limit(scrollback)
+        .build()
 }
 
+fn resize_vt(vt: &mut avt::Vt, cols: usize, rows: usize) -> Vec<usize> {
+    vt.resize(cols, rows).lines
+}
+
+/// Streams the session's events to the client. Replies to requests only go
+/// to the client which sent them, so the ones without a client, e.g. viewers,
+/// get none.
 pub async fn stream(
     clients_tx: &mpsc::Sender<Client>,
+    client: Option<ClientId>,
 ) -> Result<impl Stream<Item = Result<Event, BroadcastStreamRecvError>>> {
     let (sub_tx, sub_rx) = oneshot::channel();
-    clients_tx.send(Client(sub_tx)).await?;
+    clients_tx.send(Client::Subscribe(sub_tx)).await?;
     let sub = tokio::time::timeout(Duration::from_secs(5), sub_rx).await??;
-    let init = stream::once(future::ready(Ok(sub.init)));
-    let events = BroadcastStream::new(sub.broadcast_rx);
+    let init = stream::iter([Ok(sub.init), Ok(sub.view)]);
+
+    let events = BroadcastStream::new(sub.broadcast_rx).filter(move |event| {
+        let for_other = matches!(event, Ok(e) if e.recipient().is_some_and(|r| Some(r) != client));
+
+        future::ready(!for_other)
+    });
 
     Ok(init.chain(events))
 }
+
+/// Renders the current terminal view of the session as an image.
+pub async fn render_image(
+    clients_tx: &mpsc::Sender<Client>,
+    image: render::Image,
+) -> Result<Vec<u8>> {
+    let (image_tx, image_rx) = oneshot::channel();
+    clients_tx.send(Client::Render(image, image_tx)).await?;
+
+    Ok(image_rx.await?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{stream, Event, Session};
+    use crate::command::{ClientId, Reply};
+    use crate::render::{theme::Theme, Format};
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use futures_util::{Stream, StreamExt};
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+
+    fn reply(client: ClientId, id: Option<serde_json::Value>) -> Reply {
+        Reply { client, id }
+    }
+
+    async fn next(
+        events: &mut (impl Stream<Item = Result<Event, BroadcastStreamRecvError>> + Unpin),
+    ) -> serde_json::Value {
+        events.next().await.unwrap().unwrap().to_json()
+    }
+
+    #[test]
+    fn control() {
+        let mut session = Session::new(8, 3, 1, Duration::from_secs(1), 100, Theme::default());
+        let mut events = session.subscribe().broadcast_rx;
+        let (agent, user) = (ClientId::default(), ClientId::next());
+
+        assert!(!session.is_controlled_by_other(agent));
+
+        session.take_control(reply(user, Some(json!(1))));
+        assert!(session.is_controlled_by_other(agent));
+        assert!(!session.is_controlled_by_other(user));
+        assert_eq!(
+            events.try_recv().unwrap().to_json()["data"]["driver"],
+            json!(user)
+        );
+        assert!(matches!(events.try_recv(), Ok(Event::Ack(_))));
+
+        let init = session.subscribe().init.to_json();
+        assert_eq!(init["data"]["driver"], json!(user));
+
+        session.take_control(reply(agent, Some(json!(2))));
+        assert!(matches!(events.try_recv(), Ok(Event::Error(_, _))));
+
+        // releasing control which the client doesn't have changes nothing
+        session.release_control(reply(agent, None));
+        assert!(session.is_controlled_by_other(agent));
+        assert!(events.try_recv().is_err());
+
+        session.release_control(reply(user, None));
+        assert!(!session.is_controlled_by_other(agent));
+        assert!(matches!(events.try_recv(), Ok(Event::Control(None))));
+    }
+
+    #[tokio::test]
+    async fn screenshot() {
+        let session = Session::new(8, 3, 1, Duration::from_secs(1), 100, Theme::default());
+        let mut events = session.subscribe().broadcast_rx;
+
+        session.screenshot(reply(ClientId::default(), Some(json!(1))));
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .to_json();
+
+        let png = BASE64_STANDARD
+            .decode(event["data"]["png"].as_str().unwrap())
+            .unwrap();
+
+        assert_eq!(event["id"], 1);
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    #[tokio::test]
+    async fn replies() {
+        let mut session = Session::new(8, 3, 1, Duration::from_secs(1), 100, Theme::default());
+        let (clients_tx, mut clients_rx) = mpsc::channel(1);
+        let (a, b) = (ClientId::next(), ClientId::next());
+
+        let serve = async {
+            for _ in 0..3 {
+                session.serve(clients_rx.recv().await.unwrap());
+            }
+        };
+
+        let (_, a_events, b_events, viewer_events) = tokio::join!(
+            serve,
+            stream(&clients_tx, Some(a)),
+            stream(&clients_tx, Some(b)),
+            stream(&clients_tx, None)
+        );
+
+        // skipping init and view events
+        let mut a_events = Box::pin(a_events.unwrap().skip(2));
+        let mut b_events = Box::pin(b_events.unwrap().skip(2));
+        let mut viewer_events = Box::pin(viewer_events.unwrap().skip(2));
+
+        session.ack(reply(a, Some(json!(1))));
+        session.error(reply(b, Some(json!(1))), "nope");
+        session.snapshot(Some(reply(b, Some(json!(2)))), Format::Text);
+        session.exit(crate::pty::ExitStatus::Exited(0));
+
+        let event = next(&mut a_events).await;
+        assert_eq!(
+            (event["type"].as_str(), &event["id"]),
+            (Some("ack"), &json!(1))
+        );
+        assert_eq!(next(&mut a_events).await["type"], "snapshot");
+        assert_eq!(next(&mut a_events).await["type"], "exit");
+
+        let event = next(&mut b_events).await;
+        assert_eq!(
+            (event["type"].as_str(), &event["id"]),
+            (Some("error"), &json!(1))
+        );
+        assert_eq!(next(&mut b_events).await["id"], 2);
+        assert_eq!(next(&mut b_events).await["type"], "snapshot");
+        assert_eq!(next(&mut b_events).await["type"], "exit");
+
+        // the final snapshot on exit is for everyone
+        let event = next(&mut viewer_events).await;
+        assert_eq!(
+            (event["type"].as_str(), event.get("id")),
+            (Some("snapshot"), None)
+        );
+        assert_eq!(next(&mut viewer_events).await["type"], "exit");
+    }
+}
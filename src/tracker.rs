@@ -0,0 +1,406 @@
+/// Tracks terminal modes which avt doesn't expose, by scanning the output
+/// for the relevant control sequences. It also picks up things the
+/// application reports through the terminal, like window title changes.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    state: State,
+    params: String,
+    osc: String,
+    modes: Modes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Report {
+    Title(String),
+    Bell,
+    Notification(Option<String>, String),
+}
+
+/// Longer OSC strings are truncated.
+const MAX_OSC_LEN: usize = 4096;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Modes {
+    pub alternate_screen: bool,
+    pub bracketed_paste: bool,
+    pub mouse_tracking: MouseTracking,
+    pub mouse_encoding: MouseEncoding,
+    pub cursor_key_app: bool,
+    pub keypad_app: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MouseTracking {
+    #[default]
+    None,
+    X10,
+    Normal,
+    Button,
+    Any,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MouseEncoding {
+    #[default]
+    Default,
+    Utf8,
+    Sgr,
+    Urxvt,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Osc,
+    String,
+}
+
+impl Tracker {
+    pub fn feed_str(&mut self, s: &str) -> Vec<Report> {
+        let mut reports = Vec::new();
+
+        for ch in s.chars() {
+            if let Some(report) = self.feed(ch) {
+                reports.push(report);
+            }
+        }
+
+        reports
+    }
+
+    pub fn modes(&self) -> Modes {
+        self.modes
+    }
+
+    fn feed(&mut self, ch: char) -> Option<Report> {
+        use State::*;
+
+        let mut report = None;
+
+        self.state = match (self.state, ch) {
+            // as with other strings, ESC ends OSC, so ESC \ (ST) terminates it
+            // and any other escape sequence is processed as such
+            (Osc, '\x1b') => {
+                report = self.osc_dispatch();
+                Escape
+            }
+
+            (Osc, '\x07' | '\u{9c}') => {
+                report = self.osc_dispatch();
+                Ground
+            }
+
+            (Osc, _) => {
+                if self.osc.len() < MAX_OSC_LEN {
+                    self.osc.push(ch);
+                }
+
+                Osc
+            }
+
+            // ESC ends the string, as in avt's parser, and starts an escape
+            // sequence, which is ignored when it's ST (ESC \)
+            (String, '\x1b') => Escape,
+            (String, '\x07' | '\u{9c}') => Ground,
+            (String, _) => String,
+            (_, '\x1b') => Escape,
+            (_, '\u{9b}') => self.csi_entry(),
+            (_, '\u{9d}') => self.osc_entry(),
+            (_, '\u{90}' | '\u{98}' | '\u{9e}' | '\u{9f}') => String,
+            (_, '\x18' | '\x1a') => Ground,
+
+            (Escape, '[') => self.csi_entry(),
+            (Escape, ']') => self.osc_entry(),
+            (Escape, 'P' | 'X' | '^' | '_') => String,
+            (Escape, '\x20'..='\x2f') => Escape,
+            (Escape, '=') => self.keypad(true),
+            (Escape, '>') => self.keypad(false),
+            (Escape, 'c') => self.reset(),
+            (Escape, _) => Ground,
+
+            (Csi, '\x30'..='\x3f') => {
+                self.params.push(ch);
+                Csi
+            }
+
+            (Csi, '\x20'..='\x2f') => {
+                self.params.push(ch);
+                Csi
+            }
+
+            (Csi, '\x40'..='\x7e') => self.csi_dispatch(ch),
+            (Csi, _) => Csi,
+
+            (Ground, '\x07') => {
+                report = Some(Report::Bell);
+                Ground
+            }
+
+            (Ground, _) => Ground,
+        };
+
+        report
+    }
+
+    fn csi_entry(&mut self) -> State {
+        self.params.clear();
+
+        State::Csi
+    }
+
+    fn osc_entry(&mut self) -> State {
+        self.osc.clear();
+
+        State::Osc
+    }
+
+    fn osc_dispatch(&mut self) -> Option<Report> {
+        let osc = std::mem::take(&mut self.osc);
+        let (command, data) = osc.split_once(';')?;
+
+        match command {
+            "0" | "2" => Some(Report::Title(data.to_owned())),
+
+            // OSC 9;4 is used for progress reporting (ConEmu, Windows Terminal)
+            "9" if data.starts_with("4;") => None,
+
+            "9" => Some(Report::Notification(None, data.to_owned())),
+
+            "777" => {
+                let (title, body) = data.strip_prefix("notify;")?.split_once(';')?;
+
+                Some(Report::Notification(
+                    Some(title.to_owned()),
+                    body.to_owned(),
+                ))
+            }
+
+            _ => None,
+        }
+    }
+
+    fn csi_dispatch(&mut self, ch: char) -> State {
+        let params = std::mem::take(&mut self.params);
+
+        if let Some(params) = params.strip_prefix('?') {
+            let set = match ch {
+                'h' => true,
+                'l' => false,
+                _ => return State::Ground,
+            };
+
+            for mode in params.split(';').filter_map(|p| p.parse::<u16>().ok()) {
+                self.set_dec_mode(mode, set);
+            }
+        }
+
+        State::Ground
+    }
+
+    fn set_dec_mode(&mut self, mode: u16, set: bool) {
+        let modes = &mut self.modes;
+
+        match mode {
+            47 | 1047 | 1049 => modes.alternate_screen = set,
+            2004 => modes.bracketed_paste = set,
+
+            9 | 1000 | 1002 | 1003 => {
+                modes.mouse_tracking = match (set, mode) {
+                    (false, _) => MouseTracking::None,
+                    (true, 9) => MouseTracking::X10,
+                    (true, 1000) => MouseTracking::Normal,
+                    (true, 1002) => MouseTracking::Button,
+                    (true, _) => MouseTracking::Any,
+                };
+            }
+
+            1005 | 1006 | 1015 => {
+                let encoding = match mode {
+                    1005 => MouseEncoding::Utf8,
+                    1006 => MouseEncoding::Sgr,
+                    _ => MouseEncoding::Urxvt,
+                };
+
+                if set {
+                    modes.mouse_encoding = encoding;
+                } else if modes.mouse_encoding == encoding {
+                    modes.mouse_encoding = MouseEncoding::Default;
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    fn keypad(&mut self, app: bool) -> State {
+        self.modes.keypad_app = app;
+
+        State::Ground
+    }
+
+    fn reset(&mut self) -> State {
+        self.modes = Modes::default();
+
+        State::Ground
+    }
+}
+
+impl MouseTracking {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MouseTracking::None => "none",
+            MouseTracking::X10 => "x10",
+            MouseTracking::Normal => "normal",
+            MouseTracking::Button => "button",
+            MouseTracking::Any => "any",
+        }
+    }
+}
+
+impl MouseEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MouseEncoding::Default => "default",
+            MouseEncoding::Utf8 => "utf8",
+            MouseEncoding::Sgr => "sgr",
+            MouseEncoding::Urxvt => "urxvt",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MouseEncoding, MouseTracking, Report, Tracker};
+
+    #[test]
+    fn dec_modes() {
+        let mut tracker = Tracker::default();
+
+        tracker.feed_str("\x1b[?1049h\x1b[?2004h\x1b[?1002;1006h");
+        let modes = tracker.modes();
+
+        assert!(modes.alternate_screen);
+        assert!(modes.bracketed_paste);
+        assert_eq!(modes.mouse_tracking, MouseTracking::Button);
+        assert_eq!(modes.mouse_encoding, MouseEncoding::Sgr);
+
+        tracker.feed_str("\x1b[?1049l\x1b[?2004l\x1b[?1002l\x1b[?1005l");
+        let modes = tracker.modes();
+
+        assert!(!modes.alternate_screen);
+        assert!(!modes.bracketed_paste);
+        assert_eq!(modes.mouse_tracking, MouseTracking::None);
+        assert_eq!(modes.mouse_encoding, MouseEncoding::Sgr);
+    }
+
+    #[test]
+    fn keypad_mode() {
+        let mut tracker = Tracker::default();
+
+        tracker.feed_str("\x1b=");
+        assert!(tracker.modes().keypad_app);
+
+        tracker.feed_str("\x1b>");
+        assert!(!tracker.modes().keypad_app);
+    }
+
+    #[test]
+    fn split_sequences() {
+        let mut tracker = Tracker::default();
+
+        tracker.feed_str("\x1b[?20");
+        tracker.feed_str("04h");
+
+        assert!(tracker.modes().bracketed_paste);
+    }
+
+    #[test]
+    fn strings_are_skipped() {
+        let mut tracker = Tracker::default();
+
+        tracker.feed_str("\x1b]0;[?2004h\x07\x1bP[?2004h=\x1b\\\x1b_=\x07");
+        let modes = tracker.modes();
+
+        assert!(!modes.bracketed_paste);
+        assert!(!modes.keypad_app);
+    }
+
+    #[test]
+    fn escape_ends_strings() {
+        let mut tracker = Tracker::default();
+
+        tracker.feed_str("\x1bPfoo\x1b[?2004h");
+        assert!(tracker.modes().bracketed_paste);
+
+        tracker.feed_str("\x1b^bar\x1b=");
+        assert!(tracker.modes().keypad_app);
+    }
+
+    #[test]
+    fn reset() {
+        let mut tracker = Tracker::default();
+
+        tracker.feed_str("\x1b[?2004h\x1b=\x1bc");
+
+        assert_eq!(tracker.modes(), Default::default());
+    }
+
+    #[test]
+    fn title() {
+        let mut tracker = Tracker::default();
+
+        assert_eq!(
+            tracker.feed_str("\x1b]0;vim foo.rs\x07\x1b]2;bar\x1b\\\x1b]1;icon\x07"),
+            vec![
+                Report::Title("vim foo.rs".to_owned()),
+                Report::Title("bar".to_owned())
+            ]
+        );
+
+        assert_eq!(
+            tracker.feed_str("\x1b]2;interrupted\x1b[?2004h"),
+            vec![Report::Title("interrupted".to_owned())]
+        );
+
+        assert!(tracker.modes().bracketed_paste);
+
+        tracker.feed_str("\x1b]2;split ");
+
+        assert_eq!(
+            tracker.feed_str("title\x07"),
+            vec![Report::Title("split title".to_owned())]
+        );
+    }
+
+    #[test]
+    fn bell() {
+        let mut tracker = Tracker::default();
+
+        assert_eq!(tracker.feed_str("a\x07b"), vec![Report::Bell]);
+        assert!(tracker
+            .feed_str("\x1b]0;x\x07")
+            .iter()
+            .all(|r| *r != Report::Bell));
+    }
+
+    #[test]
+    fn notification() {
+        let mut tracker = Tracker::default();
+
+        assert_eq!(
+            tracker.feed_str("\x1b]9;build done\x07\x1b]9;4;1;50\x07"),
+            vec![Report::Notification(None, "build done".to_owned())]
+        );
+
+        assert_eq!(
+            tracker.feed_str("\x1b]777;notify;make;tests passed\x1b\\"),
+            vec![Report::Notification(
+                Some("make".to_owned()),
+                "tests passed".to_owned()
+            )]
+        );
+    }
+}
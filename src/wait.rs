@@ -0,0 +1,290 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub enum Pattern {
+    Text(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Region {
+    pub col: usize,
+    pub row: usize,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub col: usize,
+    pub row: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Matched(Match),
+    Idle,
+    Timeout,
+}
+
+#[derive(Debug)]
+enum Condition {
+    Pattern(Pattern, Option<Region>),
+    Idle(Duration),
+}
+
+#[derive(Debug)]
+pub struct Wait {
+    condition: Condition,
+    start_time: Instant,
+    deadline: Option<Instant>,
+}
+
+impl Wait {
+    pub fn new(pattern: Pattern, region: Option<Region>, timeout: Option<Duration>) -> Self {
+        Self::build(Condition::Pattern(pattern, region), timeout)
+    }
+
+    pub fn idle(period: Duration, timeout: Option<Duration>) -> Self {
+        Self::build(Condition::Idle(period), timeout)
+    }
+
+    fn build(condition: Condition, timeout: Option<Duration>) -> Self {
+        let now = Instant::now();
+
+        Self {
+            condition,
+            start_time: now,
+            deadline: timeout.map(|t| now + t),
+        }
+    }
+
+    /// Returns the outcome of the wait if it's settled at `now`, given the
+    /// current view and the time of the most recent output.
+    pub fn check(&self, vt: &avt::Vt, last_output_time: Instant, now: Instant) -> Option<Outcome> {
+        if let Some(m) = self.find(vt) {
+            return Some(Outcome::Matched(m));
+        }
+
+        if self.idle_time(last_output_time).is_some_and(|t| t <= now) {
+            return Some(Outcome::Idle);
+        }
+
+        if self.deadline.is_some_and(|d| d <= now) {
+            return Some(Outcome::Timeout);
+        }
+
+        None
+    }
+
+    /// Returns the earliest time at which the wait may settle without any
+    /// further output.
+    pub fn next_deadline(&self, last_output_time: Instant) -> Option<Instant> {
+        match (self.idle_time(last_output_time), self.deadline) {
+            (Some(t1), Some(t2)) => Some(t1.min(t2)),
+            (t1, t2) => t1.or(t2),
+        }
+    }
+
+    fn idle_time(&self, last_output_time: Instant) -> Option<Instant> {
+        match self.condition {
+            Condition::Idle(period) => Some(self.start_time.max(last_output_time) + period),
+            Condition::Pattern(_, _) => None,
+        }
+    }
+
+    pub fn find(&self, vt: &avt::Vt) -> Option<Match> {
+        let Condition::Pattern(pattern, region) = &self.condition else {
+            return None;
+        };
+
+        let (cols, rows) = vt.size();
+        let region = region.unwrap_or(Region {
+            col: 0,
+            row: 0,
+            cols,
+            rows,
+        });
+
+        let text = RegionText::new(vt.view(), region);
+
+        let (start, end) = match pattern {
+            Pattern::Text(s) => text.text.find(s.as_str()).map(|i| (i, i + s.len()))?,
+            Pattern::Regex(re) => re.find(&text.text).map(|m| (m.start(), m.end()))?,
+        };
+
+        // an empty match in a region outside of the screen has no position
+        let (col, row) = text.position(start)?;
+
+        Some(Match {
+            col,
+            row,
+            text: text.text[start..end].to_owned(),
+        })
+    }
+}
+
+/// Text of a screen region, with rows joined by newlines, and a mapping
+/// of byte offsets in that text back to screen coordinates.
+struct RegionText {
+    text: String,
+    positions: Vec<(usize, usize, usize)>,
+}
+
+impl RegionText {
+    fn new(view: &[avt::Line], region: Region) -> Self {
+        let mut text = String::new();
+        let mut positions = Vec::new();
+        let last_row = (region.row + region.rows).min(view.len());
+
+        for (row, line) in view.iter().enumerate().take(last_row).skip(region.row) {
+            if row > region.row {
+                positions.push((text.len(), region.col + region.cols, row - 1));
+                text.push('\n');
+            }
+
+            let last_col = (region.col + region.cols).min(line.len());
+
+            for (col, cell) in line.cells().iter().enumerate().take(last_col) {
+                if col >= region.col && cell.width() > 0 {
+                    positions.push((text.len(), col, row));
+                    text.push(cell.char());
+                }
+            }
+        }
+
+        Self { text, positions }
+    }
+
+    fn position(&self, offset: usize) -> Option<(usize, usize)> {
+        let i = self.positions.partition_point(|(o, _, _)| *o <= offset);
+        let (_, col, row) = self.positions.get(i.saturating_sub(1))?;
+
+        Some((*col, *row))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Outcome, Pattern, Region, Wait};
+    use regex::Regex;
+    use std::time::{Duration, Instant};
+
+    fn vt(input: &str) -> avt::Vt {
+        let mut vt = avt::Vt::new(10, 3);
+        vt.feed_str(input);
+
+        vt
+    }
+
+    #[test]
+    fn find_text() {
+        let wait = Wait::new(Pattern::Text("bar".to_owned()), None, None);
+        let m = wait.find(&vt("foo\r\n  bar")).unwrap();
+
+        assert_eq!((m.col, m.row, m.text.as_str()), (2, 1, "bar"));
+        assert!(wait.find(&vt("foo")).is_none());
+    }
+
+    #[test]
+    fn find_regex() {
+        let re = Regex::new(r"\d+ files").unwrap();
+        let wait = Wait::new(Pattern::Regex(re), None, None);
+        let m = wait.find(&vt("\r\nok 3 files")).unwrap();
+
+        assert_eq!((m.col, m.row, m.text.as_str()), (3, 1, "3 files"));
+    }
+
+    #[test]
+    fn find_in_region() {
+        let region = Region {
+            col: 4,
+            row: 1,
+            cols: 6,
+            rows: 2,
+        };
+
+        let wait = Wait::new(Pattern::Text("foo".to_owned()), Some(region), None);
+
+        assert!(wait.find(&vt("foo")).is_none());
+        assert!(wait.find(&vt("\r\nfoo")).is_none());
+
+        let m = wait.find(&vt("\r\n\r\n     foo")).unwrap();
+
+        assert_eq!((m.col, m.row), (5, 2));
+    }
+
+    #[test]
+    fn find_in_empty_region() {
+        let region = Region {
+            col: 20,
+            row: 5,
+            cols: 10,
+            rows: 2,
+        };
+
+        let wait = Wait::new(
+            Pattern::Regex(Regex::new("x*").unwrap()),
+            Some(region),
+            None,
+        );
+
+        assert!(wait.find(&vt("foo")).is_none());
+
+        let wait = Wait::new(Pattern::Text(String::new()), Some(region), None);
+
+        assert!(wait.find(&vt("foo")).is_none());
+    }
+
+    #[test]
+    fn find_wide_chars() {
+        let wait = Wait::new(Pattern::Text("x".to_owned()), None, None);
+        let m = wait.find(&vt("日本x")).unwrap();
+
+        assert_eq!((m.col, m.row), (4, 0));
+    }
+
+    #[test]
+    fn check_idle() {
+        let vt = vt("");
+        let wait = Wait::idle(Duration::from_millis(100), Some(Duration::from_millis(500)));
+        let now = Instant::now();
+        let check = |last_output: Instant, at: Instant| wait.check(&vt, last_output, at);
+
+        assert!(check(now, now).is_none());
+        assert!(matches!(
+            check(now, now + Duration::from_millis(150)),
+            Some(Outcome::Idle)
+        ));
+
+        let last_output = now + Duration::from_millis(100);
+
+        assert!(check(last_output, now + Duration::from_millis(150)).is_none());
+
+        assert!(matches!(
+            check(
+                now + Duration::from_millis(450),
+                now + Duration::from_millis(500)
+            ),
+            Some(Outcome::Timeout)
+        ));
+    }
+
+    #[test]
+    fn check_timeout() {
+        let wait = Wait::new(Pattern::Text("x".to_owned()), None, Some(Duration::ZERO));
+        let now = Instant::now();
+
+        assert!(matches!(
+            wait.check(&vt(""), now, now),
+            Some(Outcome::Timeout)
+        ));
+        assert!(matches!(
+            wait.check(&vt("x"), now, now),
+            Some(Outcome::Matched(_))
+        ));
+    }
+}